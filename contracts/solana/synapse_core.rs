@@ -1,9 +1,37 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use std::collections::HashMap;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::{ed25519_program, sysvar};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 declare_id!("SYNPSv1protocol11111111111111111111111111111");
 
+pub const MAX_DELEGATION_DEPTH: usize = 8;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// Fixed-point scale for the reward-per-stake accumulator
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+// Node count at which the decentralization score's size component saturates
+pub const DECENTRALIZATION_TARGET_NODES: u64 = 100;
+// Ballot slots per side of a Proposal. A multiple of 8 so the per-voter
+// bitmaps are whole bytes.
+pub const MAX_PROPOSAL_VOTERS: usize = 256;
+// Deepest Merkle proof verify_inclusion accepts; the leaf index is a u32
+pub const MAX_MERKLE_DEPTH: usize = 32;
+// Byte cap on NodeState.metadata_uri
+pub const MAX_METADATA_URI_LEN: usize = 128;
+// Open proposals whose roots are checked for duplicates
+pub const MAX_PENDING_ROOTS: usize = 16;
+// Accepted roots kept in the ProtocolState checkpoint ring buffer
+pub const MAX_CHECKPOINTS: usize = 8;
+
+// Layout of an Ed25519SigVerify instruction carrying a single signature
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+const ED25519_PUBKEY_LEN: usize = 32;
+const ED25519_SIGNATURE_LEN: usize = 64;
+
 #[program]
 pub mod synapse_protocol {
     use super::*;
@@ -20,328 +48,2846 @@ pub mod synapse_protocol {
         protocol_state.active_node_count = 0;
         protocol_state.proposal_count = 0;
         protocol_state.last_epoch_update = Clock::get()?.unix_timestamp;
+        protocol_state.sync_window = 0;
+        protocol_state.execution_delay = 0;
+        protocol_state.min_active_epochs = 0;
+        protocol_state.min_distinct_regions = 0;
+        protocol_state.computation_difficulty = 0;
+        protocol_state.cleanup_delay = epoch_duration;
+        protocol_state.require_independent_vote = true;
+        protocol_state.reputation_reward = 10;
+        protocol_state.slash_bps = 1_000;
+        protocol_state.slash_reputation_penalty = 100;
+        protocol_state.epoch = 0;
+        protocol_state.unstake_cooldown = epoch_duration;
+        protocol_state.max_missed_epochs = 0;
+        protocol_state.total_active_stake = 0;
+        protocol_state.stake_weighted = false;
+        protocol_state.recent_proposer_epochs = 0;
+        protocol_state.recent_proposer_penalty_bps = 0;
+        protocol_state.proposal_ttl = 0;
+        protocol_state.min_client_version = 0;
+        // Over a third against leaves the default 67% approval out of reach
+        protocol_state.rejection_threshold_bps = 3_400;
+        protocol_state.pending_admin = None;
+        protocol_state.paused = false;
+        protocol_state.reputation_participation_floor = 0;
+        protocol_state.consensus_threshold_bps = 6_700;
+        protocol_state.reputation_decay_bps = 0;
+        protocol_state.reputation_floor = 0;
+        protocol_state.reward_pool = Pubkey::default();
+        protocol_state.epoch_reward = 0;
+        protocol_state.reward_per_stake = 0;
+        protocol_state.last_rewarded_epoch = 0;
+        protocol_state.reward_liabilities = 0;
+        protocol_state.min_reputation_vote_interval = 0;
+        protocol_state.slash_redistribution_bps = 5_000;
+        protocol_state.min_proposer_reputation = 0;
+        protocol_state.min_proposer_stake = 0;
+        protocol_state.proposal_cooldown = 0;
+        protocol_state.checkpoints = [Checkpoint::default(); MAX_CHECKPOINTS];
+        protocol_state.checkpoint_count = 0;
+        protocol_state.stake_mint = Pubkey::default();
+        protocol_state.protocol_vault = Pubkey::default();
+        protocol_state.pending_roots = [PendingRoot::default(); MAX_PENDING_ROOTS];
+        protocol_state.total_staked = 0;
+        protocol_state.challenge_period = 0;
+        protocol_state.challenge_bond = min_stake;
+        protocol_state.forfeited_bonds = 0;
+        protocol_state.challenge_resolution_period = 0;
         Ok(())
     }
 
-    pub fn register_node(
-        ctx: Context<RegisterNode>,
-        stake_amount: u64,
-        neural_state_root: [u8; 32],
-    ) -> Result<()> {
+    pub fn set_sync_window(ctx: Context<AdminOnly>, sync_window: i64) -> Result<()> {
         let protocol_state = &mut ctx.accounts.protocol_state;
-        let node_state = &mut ctx.accounts.node_state;
-        let clock = Clock::get()?;
 
         require!(
-            stake_amount >= protocol_state.min_stake,
-            SynapseError::InsufficientStake
-        );
-
-        // Transfer stake tokens
-        let transfer_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.staker_token_account.to_account_info(),
-                to: ctx.accounts.protocol_vault.to_account_info(),
-                authority: ctx.accounts.staker.to_account_info(),
-            },
+            sync_window >= 0 && sync_window < protocol_state.epoch_duration,
+            SynapseError::InvalidParameter
         );
-        token::transfer(transfer_ctx, stake_amount)?;
-
-        // Initialize node state
-        node_state.stake = stake_amount;
-        node_state.reputation = 1000; // Base reputation
-        node_state.last_update = clock.unix_timestamp;
-        node_state.neural_state_root = neural_state_root;
-        node_state.is_active = true;
-        node_state.owner = ctx.accounts.staker.key();
 
-        protocol_state.active_node_count += 1;
+        protocol_state.sync_window = sync_window;
+        Ok(())
+    }
 
-        emit!(NodeRegistered {
-            node: ctx.accounts.staker.key(),
-            stake: stake_amount,
-            timestamp: clock.unix_timestamp,
-        });
+    pub fn set_execution_delay(ctx: Context<AdminOnly>, execution_delay: i64) -> Result<()> {
+        require!(execution_delay >= 0, SynapseError::InvalidParameter);
 
+        ctx.accounts.protocol_state.execution_delay = execution_delay;
         Ok(())
     }
 
-    pub fn propose_neural_state(
-        ctx: Context<ProposeNeuralState>,
-        neural_state_root: [u8; 32],
-        signature: Vec<u8>,
+    // A zero challenge_period disables disputes and leaves execution to
+    // execute_proposal. Challenges left unresolved for
+    // challenge_resolution_period can be expired by anyone, so disputes don't
+    // depend on the admin staying around.
+    pub fn set_challenge_params(
+        ctx: Context<AdminOnly>,
+        challenge_period: i64,
+        challenge_bond: u64,
+        challenge_resolution_period: i64,
     ) -> Result<()> {
-        let protocol_state = &mut ctx.accounts.protocol_state;
-        let node_state = &mut ctx.accounts.node_state;
-        let proposal = &mut ctx.accounts.proposal;
-        let clock = Clock::get()?;
-
-        require!(node_state.is_active, SynapseError::NodeNotActive);
+        require!(challenge_period >= 0, SynapseError::InvalidParameter);
         require!(
-            verify_signature(&neural_state_root, &signature, &ctx.accounts.staker.key()),
-            SynapseError::InvalidSignature
+            challenge_period == 0 || challenge_resolution_period > 0,
+            SynapseError::InvalidParameter
         );
 
-        let proposal_id = protocol_state.proposal_count;
-        protocol_state.proposal_count += 1;
-
-        proposal.neural_state_root = neural_state_root;
-        proposal.timestamp = clock.unix_timestamp;
-        proposal.proposer = ctx.accounts.staker.key();
-        proposal.vote_count = 1;
-        proposal.executed = false;
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        protocol_state.challenge_period = challenge_period;
+        protocol_state.challenge_bond = challenge_bond;
+        protocol_state.challenge_resolution_period = challenge_resolution_period;
+        Ok(())
+    }
 
-        // Auto-vote by proposer
-        proposal.votes.insert(ctx.accounts.staker.key(), true);
+    pub fn set_min_active_epochs(ctx: Context<AdminOnly>, min_active_epochs: u64) -> Result<()> {
+        ctx.accounts.protocol_state.min_active_epochs = min_active_epochs;
+        Ok(())
+    }
 
-        emit!(ProposalCreated {
-            proposal_id,
-            neural_state_root,
-            proposer: ctx.accounts.staker.key(),
-            timestamp: clock.unix_timestamp,
-        });
+    pub fn set_min_distinct_regions(
+        ctx: Context<AdminOnly>,
+        min_distinct_regions: u8,
+    ) -> Result<()> {
+        ctx.accounts.protocol_state.min_distinct_regions = min_distinct_regions;
+        Ok(())
+    }
 
+    pub fn set_computation_difficulty(
+        ctx: Context<AdminOnly>,
+        computation_difficulty: u8,
+    ) -> Result<()> {
+        ctx.accounts.protocol_state.computation_difficulty = computation_difficulty;
         Ok(())
     }
 
-    pub fn vote_on_proposal(ctx: Context<VoteOnProposal>) -> Result<()> {
-        let protocol_state = &ctx.accounts.protocol_state;
-        let node_state = &ctx.accounts.node_state;
-        let proposal = &mut ctx.accounts.proposal;
+    pub fn set_cleanup_delay(ctx: Context<AdminOnly>, cleanup_delay: i64) -> Result<()> {
+        require!(cleanup_delay >= 0, SynapseError::InvalidParameter);
 
-        require!(node_state.is_active, SynapseError::NodeNotActive);
-        require!(!proposal.executed, SynapseError::ProposalAlreadyExecuted);
-        require!(
-            !proposal.votes.contains_key(&ctx.accounts.voter.key()),
-            SynapseError::AlreadyVoted
-        );
+        ctx.accounts.protocol_state.cleanup_delay = cleanup_delay;
+        Ok(())
+    }
 
-        proposal.votes.insert(ctx.accounts.voter.key(), true);
-        proposal.vote_count += 1;
+    pub fn set_require_independent_vote(
+        ctx: Context<AdminOnly>,
+        require_independent_vote: bool,
+    ) -> Result<()> {
+        ctx.accounts.protocol_state.require_independent_vote = require_independent_vote;
+        Ok(())
+    }
 
-        // Check for consensus threshold (67%)
-        if proposal.vote_count * 100 >= protocol_state.active_node_count * 67 {
-            proposal.executed = true;
-            update_reputations(ctx.accounts.proposal, protocol_state)?;
+    pub fn set_reputation_reward(ctx: Context<AdminOnly>, reputation_reward: u64) -> Result<()> {
+        ctx.accounts.protocol_state.reputation_reward = reputation_reward;
+        Ok(())
+    }
 
-            emit!(ConsensusReached {
-                proposal_id: ctx.accounts.proposal.key(),
-                neural_state_root: proposal.neural_state_root,
-                timestamp: Clock::get()?.unix_timestamp,
-            });
-        }
+    pub fn set_slash_params(
+        ctx: Context<AdminOnly>,
+        slash_bps: u64,
+        slash_reputation_penalty: u64,
+    ) -> Result<()> {
+        require!(slash_bps <= BPS_DENOMINATOR, SynapseError::InvalidParameter);
 
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        protocol_state.slash_bps = slash_bps;
+        protocol_state.slash_reputation_penalty = slash_reputation_penalty;
         Ok(())
     }
 
-    pub fn update_stake(
-        ctx: Context<UpdateStake>,
-        amount: u64,
-        increase: bool,
+    pub fn set_slash_redistribution_bps(
+        ctx: Context<AdminOnly>,
+        slash_redistribution_bps: u64,
     ) -> Result<()> {
-        let protocol_state = &ctx.accounts.protocol_state;
-        let node_state = &mut ctx.accounts.node_state;
+        require!(
+            slash_redistribution_bps <= BPS_DENOMINATOR,
+            SynapseError::InvalidParameter
+        );
 
-        require!(node_state.is_active, SynapseError::NodeNotActive);
+        ctx.accounts.protocol_state.slash_redistribution_bps = slash_redistribution_bps;
+        Ok(())
+    }
 
-        if increase {
-            // Transfer additional stake
-            let transfer_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.staker_token_account.to_account_info(),
-                    to: ctx.accounts.protocol_vault.to_account_info(),
-                    authority: ctx.accounts.staker.to_account_info(),
-                },
-            );
-            token::transfer(transfer_ctx, amount)?;
-            node_state.stake += amount;
+    // Pays slash_redistribution_bps of a node's unsettled slash to the nodes
+    // that voted against it on the proposal it was slashed over, pro rata to
+    // the weight their ballots carried. Paginated: remaining_accounts hold the
+    // winning side's nodes in vote order from `start`, and each voter's bit in
+    // slash_settled keeps it from being paid twice. A node closed since is
+    // passed as its empty address and its share stays with the protocol. The
+    // tokens never leave the vault; they move into the recipients' stake.
+    pub fn distribute_slash_to_honest_voters(
+        ctx: Context<DistributeSlash>,
+        start: u16,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let slashed_node = &mut ctx.accounts.slashed_node;
+        let proposal = ctx.accounts.proposal.load()?;
 
-            emit!(StakeIncreased {
-                node: ctx.accounts.staker.key(),
-                amount,
-                timestamp: Clock::get()?.unix_timestamp,
-            });
-        } else {
+        require!(
+            slashed_node.undistributed_slash > 0,
+            SynapseError::NoSlashToDistribute
+        );
+        require_keys_eq!(
+            slashed_node.slashed_proposal,
+            ctx.accounts.proposal.key(),
+            SynapseError::InvalidParameter
+        );
+        let (honest_voters, honest_weights, total_weight) = if proposal.executed != 0 {
+            require!(
+                proposal.dissenters().contains(&slashed_node.owner),
+                SynapseError::NodeVotedWithOutcome
+            );
+            (
+                proposal.voters(),
+                &proposal.voter_weights[..],
+                proposal.total_stake_voted,
+            )
+        } else if proposal.rejected != 0 {
             require!(
-                node_state.stake - amount >= protocol_state.min_stake,
-                SynapseError::InsufficientStake
+                proposal.voters().contains(&slashed_node.owner),
+                SynapseError::NodeVotedWithOutcome
             );
+            (
+                proposal.dissenters(),
+                &proposal.dissenter_weights[..],
+                proposal.total_stake_against,
+            )
+        } else {
+            return err!(SynapseError::ProposalUndecided);
+        };
+        let start = start as usize;
+        require!(
+            start + ctx.remaining_accounts.len() <= honest_voters.len(),
+            SynapseError::InvalidParameter
+        );
+        require!(total_weight > 0, SynapseError::NoActiveStake);
 
-            // Return stake to user
-            let vault_authority_seeds = &[
-                protocol_state.to_account_info().key.as_ref(),
-                &[protocol_state.vault_authority_bump],
-            ];
-            let vault_signer = &[&vault_authority_seeds[..]];
+        // The slash being paid out is fixed by the first page, so later slashes
+        // over the same proposal wait for the next round
+        if slashed_node.slash_distribution_base == 0 {
+            slashed_node.slash_distribution_base = slashed_node.undistributed_slash;
+        }
+        let amount = (slashed_node.slash_distribution_base as u128
+            * protocol_state.slash_redistribution_bps as u128
+            / BPS_DENOMINATOR as u128) as u64;
 
-            let transfer_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.protocol_vault.to_account_info(),
-                    to: ctx.accounts.staker_token_account.to_account_info(),
-                    authority: ctx.accounts.vault_authority.to_account_info(),
-                },
-                vault_signer,
+        let mut distributed: u64 = 0;
+        let mut recipients: u32 = 0;
+        for (offset, account_info) in ctx.remaining_accounts.iter().enumerate() {
+            let index = start + offset;
+            if bit_is_set(&slashed_node.slash_settled, index) {
+                continue;
+            }
+            set_bit(&mut slashed_node.slash_settled, index);
+
+            if account_info.data_is_empty() {
+                let (closed_node, _) = Pubkey::find_program_address(
+                    &[
+                        b"node",
+                        protocol_state.key().as_ref(),
+                        honest_voters[index].as_ref(),
+                    ],
+                    ctx.program_id,
+                );
+                require_keys_eq!(
+                    account_info.key(),
+                    closed_node,
+                    SynapseError::InvalidParameter
+                );
+                continue;
+            }
+            let mut node = node_account(account_info, protocol_state.key())?;
+            require_keys_eq!(
+                node.owner,
+                honest_voters[index],
+                SynapseError::InvalidParameter
             );
-            token::transfer(transfer_ctx, amount)?;
-            node_state.stake -= amount;
+            let share =
+                (amount as u128 * honest_weights[index] as u128 / total_weight as u128) as u64;
+            if share == 0 {
+                continue;
+            }
 
-            emit!(StakeDecreased {
-                node: ctx.accounts.staker.key(),
-                amount,
-                timestamp: Clock::get()?.unix_timestamp,
-            });
+            node.settle_rewards(protocol_state)?;
+            node.stake = node
+                .stake
+                .checked_add(share)
+                .ok_or(SynapseError::MathOverflow)?;
+            protocol_state.add_staked(share)?;
+            if node.is_active && !node.is_paused {
+                protocol_state.add_active_stake(share)?;
+            }
+            node.sync_reward_debt(protocol_state);
+            node.exit(ctx.program_id)?;
+            distributed = distributed
+                .checked_add(share)
+                .ok_or(SynapseError::MathOverflow)?;
+            recipients += 1;
+        }
+
+        // The round closes once every winning voter has been settled
+        let settled = (0..honest_voters.len())
+            .filter(|index| bit_is_set(&slashed_node.slash_settled, *index))
+            .count();
+        if settled == honest_voters.len() {
+            slashed_node.undistributed_slash = slashed_node
+                .undistributed_slash
+                .checked_sub(slashed_node.slash_distribution_base)
+                .ok_or(SynapseError::MathOverflow)?;
+            slashed_node.slash_distribution_base = 0;
+            slashed_node.slash_settled = [0; MAX_PROPOSAL_VOTERS / 8];
         }
 
+        emit!(SlashRedistributed {
+            slashed_node: slashed_node.owner,
+            proposal: ctx.accounts.proposal.key(),
+            amount: distributed,
+            recipients,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn deregister_node(ctx: Context<DeregisterNode>) -> Result<()> {
+    // A slash over a decided proposal is held in undistributed_slash for that
+    // proposal's honest voters; one without a proposal stays with the protocol.
+    // A node carries at most one such proposal until it has been distributed.
+    pub fn slash_node(ctx: Context<SlashNode>, reason: u8) -> Result<()> {
+        let slashed_proposal = ctx.accounts.proposal.as_ref().map(|proposal| proposal.key());
         let protocol_state = &mut ctx.accounts.protocol_state;
         let node_state = &mut ctx.accounts.node_state;
 
-        require!(node_state.is_active, SynapseError::NodeNotActive);
-
-        // Return staked tokens
-        let vault_authority_seeds = &[
-            protocol_state.to_account_info().key.as_ref(),
-            &[protocol_state.vault_authority_bump],
-        ];
-        let vault_signer = &[&vault_authority_seeds[..]];
+        // Stake queued for withdrawal stays slashable until it is claimed, so
+        // leaving the active set doesn't escape accountability
+        let slashable = node_state
+            .stake
+            .checked_add(node_state.pending_unstake)
+            .ok_or(SynapseError::MathOverflow)?;
+        require!(slashable > 0, SynapseError::NodeNotActive);
+        node_state.settle_rewards(protocol_state)?;
 
-        let transfer_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.protocol_vault.to_account_info(),
-                to: ctx.accounts.staker_token_account.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            },
-            vault_signer,
-        );
-        token::transfer(transfer_ctx, node_state.stake)?;
+        // Slashed tokens stay in the protocol vault but leave the node's
+        // balance, taken from its stake first and then from its pending unstake
+        let amount = (slashable as u128 * protocol_state.slash_bps as u128
+            / BPS_DENOMINATOR as u128) as u64;
+        let from_stake = amount.min(node_state.stake);
+        node_state.stake = node_state
+            .stake
+            .checked_sub(from_stake)
+            .ok_or(SynapseError::MathOverflow)?;
+        node_state.pending_unstake = node_state
+            .pending_unstake
+            .checked_sub(amount - from_stake)
+            .ok_or(SynapseError::MathOverflow)?;
+        if let Some(slashed_proposal) = slashed_proposal {
+            require!(
+                node_state.undistributed_slash == 0
+                    || node_state.slashed_proposal == slashed_proposal,
+                SynapseError::SlashPendingDistribution
+            );
+            node_state.slashed_proposal = slashed_proposal;
+            node_state.undistributed_slash = node_state
+                .undistributed_slash
+                .checked_add(amount)
+                .ok_or(SynapseError::MathOverflow)?;
+        }
+        protocol_state.remove_staked(from_stake)?;
+        if node_state.is_active && !node_state.is_paused {
+            protocol_state.remove_active_stake(from_stake)?;
+        }
+        node_state.reputation = node_state
+            .reputation
+            .saturating_sub(protocol_state.slash_reputation_penalty);
 
-        node_state.is_active = false;
-        node_state.stake = 0;
-        protocol_state.active_node_count -= 1;
+        if node_state.is_active && node_state.stake < protocol_state.min_stake {
+            if !node_state.is_paused {
+                protocol_state.active_node_count = protocol_state
+                    .active_node_count
+                    .checked_sub(1)
+                    .ok_or(SynapseError::MathOverflow)?;
+                protocol_state.remove_active_stake(node_state.stake)?;
+            }
+            node_state.is_active = false;
+            node_state.is_paused = false;
+        }
+        node_state.sync_reward_debt(protocol_state);
 
-        emit!(NodeDeregistered {
-            node: ctx.accounts.staker.key(),
+        emit!(NodeSlashed {
+            node: node_state.owner,
+            amount,
+            reason,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = admin, space = 8 + ProtocolState::LEN)]
-    pub protocol_state: Account<'info, ProtocolState>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    pub fn set_unstake_cooldown(ctx: Context<AdminOnly>, unstake_cooldown: i64) -> Result<()> {
+        require!(unstake_cooldown >= 0, SynapseError::InvalidParameter);
 
-#[derive(Accounts)]
-pub struct RegisterNode<'info> {
-    #[account(mut)]
-    pub protocol_state: Account<'info, ProtocolState>,
-    #[account(
-        init,
-        payer = staker,
-        space = 8 + NodeState::LEN,
-        seeds = [b"node", staker.key().as_ref()],
-        bump
-    )]
-    pub node_state: Account<'info, NodeState>,
-    #[account(mut)]
-    pub staker: Signer<'info>,
-    #[account(mut)]
-    pub staker_token_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub protocol_vault: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+        ctx.accounts.protocol_state.unstake_cooldown = unstake_cooldown;
+        Ok(())
+    }
 
-#[account]
-pub struct ProtocolState {
-    pub admin: Pubkey,
-    pub min_stake: u64,
-    pub epoch_duration: i64,
-    pub active_node_count: u64,
-    pub proposal_count: u64,
-    pub last_epoch_update: i64,
-    pub vault_authority_bump: u8,
-}
+    pub fn set_max_missed_epochs(ctx: Context<AdminOnly>, max_missed_epochs: u64) -> Result<()> {
+        // Zero disables liveness-based deactivation
+        ctx.accounts.protocol_state.max_missed_epochs = max_missed_epochs;
+        Ok(())
+    }
 
-#[account]
-pub struct NodeState {
-    pub owner: Pubkey,
-    pub stake: u64,
-    pub reputation: u64,
-    pub last_update: i64,
-    pub neural_state_root: [u8; 32],
-    pub is_active: bool,
-}
+    pub fn set_stake_weighted(ctx: Context<AdminOnly>, stake_weighted: bool) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
 
-#[account]
-pub struct Proposal {
-    pub neural_state_root: [u8; 32],
-    pub timestamp: i64,
-    pub proposer: Pubkey,
-    pub vote_count: u64,
-    pub executed: bool,
-    pub votes: HashMap<Pubkey, bool>,
-}
+        protocol_state.stake_weighted = stake_weighted;
+        protocol_state.require_voter_capacity()
+    }
 
-#[error_code]
-pub enum SynapseError {
-    #[msg("Insufficient stake amount")]
-    InsufficientStake,
-    #[msg("Node is not active")]
-    NodeNotActive,
-    #[msg("Invalid signature")]
-    InvalidSignature,
-    #[msg("Proposal already executed")]
-    ProposalAlreadyExecuted,
-    #[msg("Already voted on proposal")]
-    AlreadyVoted,
-}
+    pub fn set_recent_proposer_penalty(
+        ctx: Context<AdminOnly>,
+        recent_proposer_epochs: u64,
+        recent_proposer_penalty_bps: u64,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
 
-// Events
-#[event]
-pub struct NodeRegistered {
-    pub node: Pubkey,
-    pub stake: u64,
-    pub timestamp: i64,
-}
+        require!(
+            recent_proposer_penalty_bps <= BPS_DENOMINATOR,
+            SynapseError::InvalidParameter
+        );
 
-#[event]
-pub struct ProposalCreated {
-    pub proposal_id: u64,
-    pub neural_state_root: [u8; 32],
-    pub proposer: Pubkey,
-    pub timestamp: i64,
-}
+        protocol_state.recent_proposer_epochs = recent_proposer_epochs;
+        protocol_state.recent_proposer_penalty_bps = recent_proposer_penalty_bps;
+        Ok(())
+    }
 
-#[event]
-pub struct ConsensusReached {
-    pub proposal_id: Pubkey,
-    pub neural_state_root: [u8; 32],
-    pub timestamp: i64,
-}
+    pub fn set_proposal_ttl(ctx: Context<AdminOnly>, proposal_ttl: i64) -> Result<()> {
+        require!(proposal_ttl >= 0, SynapseError::InvalidParameter);
 
-#[event]
-pub struct StakeIncreased {
-    pub node: Pubkey,
-    pub amount: u64,
-    pub timestamp: i64,
-}
+        ctx.accounts.protocol_state.proposal_ttl = proposal_ttl;
+        Ok(())
+    }
 
-#[event]
+    pub fn set_min_client_version(ctx: Context<AdminOnly>, min_client_version: u32) -> Result<()> {
+        ctx.accounts.protocol_state.min_client_version = min_client_version;
+        Ok(())
+    }
+
+    pub fn set_rejection_threshold(
+        ctx: Context<AdminOnly>,
+        rejection_threshold_bps: u64,
+    ) -> Result<()> {
+        require!(
+            rejection_threshold_bps > 0 && rejection_threshold_bps <= BPS_DENOMINATOR,
+            SynapseError::InvalidParameter
+        );
+
+        ctx.accounts.protocol_state.rejection_threshold_bps = rejection_threshold_bps;
+        Ok(())
+    }
+
+    // Admin handover is two-step: the current admin nominates a key, which
+    // must then sign accept_admin, so a mistyped key can't take over
+    pub fn transfer_admin(ctx: Context<AdminOnly>, new_admin: Pubkey) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        protocol_state.pending_admin = Some(new_admin);
+
+        emit!(AdminTransferStarted {
+            admin: protocol_state.admin,
+            pending_admin: new_admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let new_admin = ctx.accounts.new_admin.key();
+
+        require!(
+            protocol_state.pending_admin == Some(new_admin),
+            SynapseError::Unauthorized
+        );
+
+        let previous_admin = protocol_state.admin;
+        protocol_state.admin = new_admin;
+        protocol_state.pending_admin = None;
+
+        emit!(AdminTransferred {
+            previous_admin,
+            new_admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Permanently gives up admin control; no key can sign for the zero pubkey
+    pub fn renounce_admin(ctx: Context<AdminOnly>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        // Only the admin can withdraw forfeited bonds, so they go out first
+        require!(
+            protocol_state.forfeited_bonds == 0,
+            SynapseError::ForfeitedBondsOutstanding
+        );
+
+        let previous_admin = protocol_state.admin;
+        protocol_state.admin = Pubkey::default();
+        protocol_state.pending_admin = None;
+
+        emit!(AdminTransferred {
+            previous_admin,
+            new_admin: Pubkey::default(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Emergency switch halting registrations, proposals, votes and stake
+    // top-ups. Exits (deregister_node, request_unstake, claim_unstake) stay
+    // open so users can always withdraw.
+    pub fn set_paused(ctx: Context<AdminOnly>, paused: bool) -> Result<()> {
+        ctx.accounts.protocol_state.paused = paused;
+
+        emit!(ProtocolPauseSet {
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_reputation_participation_floor(
+        ctx: Context<AdminOnly>,
+        reputation_participation_floor: u64,
+    ) -> Result<()> {
+        // Basis points of the active set; zero rewards every executed proposal
+        require!(
+            reputation_participation_floor <= BPS_DENOMINATOR,
+            SynapseError::InvalidParameter
+        );
+
+        ctx.accounts.protocol_state.reputation_participation_floor =
+            reputation_participation_floor;
+        Ok(())
+    }
+
+    pub fn set_consensus_threshold(
+        ctx: Context<AdminOnly>,
+        consensus_threshold_bps: u64,
+    ) -> Result<()> {
+        require!(
+            (5_000..=BPS_DENOMINATOR).contains(&consensus_threshold_bps),
+            SynapseError::InvalidParameter
+        );
+
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        protocol_state.consensus_threshold_bps = consensus_threshold_bps;
+        protocol_state.require_voter_capacity()
+    }
+
+    pub fn update_protocol_params(
+        ctx: Context<AdminOnly>,
+        min_stake: u64,
+        epoch_duration: i64,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        require!(min_stake > 0, SynapseError::InvalidParameter);
+        require!(
+            epoch_duration > 0 && protocol_state.sync_window < epoch_duration,
+            SynapseError::InvalidParameter
+        );
+
+        // Nodes under a raised min_stake are deactivated on their next
+        // interaction rather than here, so no node accounts are needed
+        protocol_state.min_stake = min_stake;
+        protocol_state.epoch_duration = epoch_duration;
+
+        emit!(ProtocolParamsUpdated {
+            min_stake,
+            epoch_duration,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_reputation_decay(
+        ctx: Context<AdminOnly>,
+        reputation_decay_bps: u64,
+        reputation_floor: u64,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        // Per idle epoch, scaled by the idle streak; zero disables decay
+        require!(
+            reputation_decay_bps <= BPS_DENOMINATOR,
+            SynapseError::InvalidParameter
+        );
+
+        protocol_state.reputation_decay_bps = reputation_decay_bps;
+        protocol_state.reputation_floor = reputation_floor;
+        Ok(())
+    }
+
+    // The reward pool is a token account owned by the vault authority PDA,
+    // kept apart from the stake vault so rewards never draw on stake
+    pub fn set_reward_params(ctx: Context<SetRewardParams>, epoch_reward: u64) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        protocol_state.reward_pool = ctx.accounts.reward_pool.key();
+        protocol_state.epoch_reward = epoch_reward;
+        Ok(())
+    }
+
+    pub fn set_min_reputation_vote_interval(
+        ctx: Context<AdminOnly>,
+        min_reputation_vote_interval: i64,
+    ) -> Result<()> {
+        require!(min_reputation_vote_interval >= 0, SynapseError::InvalidParameter);
+
+        ctx.accounts.protocol_state.min_reputation_vote_interval = min_reputation_vote_interval;
+        Ok(())
+    }
+
+    pub fn set_proposer_requirements(
+        ctx: Context<AdminOnly>,
+        min_proposer_reputation: u64,
+        min_proposer_stake: u64,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        protocol_state.min_proposer_reputation = min_proposer_reputation;
+        protocol_state.min_proposer_stake = min_proposer_stake;
+        Ok(())
+    }
+
+    pub fn set_proposal_cooldown(ctx: Context<AdminOnly>, proposal_cooldown: i64) -> Result<()> {
+        require!(proposal_cooldown >= 0, SynapseError::InvalidParameter);
+
+        ctx.accounts.protocol_state.proposal_cooldown = proposal_cooldown;
+        Ok(())
+    }
+
+    // One-time setup of the token account holding all stake. It must be
+    // owned by the vault authority PDA, whose bump is recorded here.
+    pub fn set_protocol_vault(ctx: Context<SetProtocolVault>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        require!(
+            protocol_state.protocol_vault == Pubkey::default(),
+            SynapseError::InvalidVault
+        );
+
+        protocol_state.protocol_vault = ctx.accounts.protocol_vault.key();
+        protocol_state.stake_mint = ctx.accounts.protocol_vault.mint;
+        protocol_state.vault_authority_bump = *ctx
+            .bumps
+            .get("vault_authority")
+            .ok_or(SynapseError::InvalidVault)?;
+        Ok(())
+    }
+
+    pub fn cleanup_inactive_nodes(ctx: Context<CleanupInactiveNodes>) -> Result<()> {
+        let protocol_state = &ctx.accounts.protocol_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        for account_info in ctx.remaining_accounts.iter() {
+            let mut node = node_account(account_info, protocol_state.key())?;
+
+            // Closing forfeits whatever the node is still owed, so rewards
+            // accrued since its last settlement count as well
+            node.settle_rewards(protocol_state)?;
+            require!(
+                !node.is_active
+                    && node.stake == 0
+                    && node.pending_unstake == 0
+                    && node.accrued_rewards == 0
+                    && node.undistributed_slash == 0
+                    && now
+                        .checked_sub(node.last_update)
+                        .ok_or(SynapseError::MathOverflow)?
+                        >= protocol_state.cleanup_delay,
+                SynapseError::NodeNotEligibleForCleanup
+            );
+
+            // Rent goes to the admin performing the cleanup
+            node.close(ctx.accounts.admin.to_account_info())?;
+
+            emit!(NodeClosed {
+                node: node.owner,
+                timestamp: now,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn register_node(
+        ctx: Context<RegisterNode>,
+        stake_amount: u64,
+        neural_state_root: [u8; 32],
+        region: u8,
+        client_version: u32,
+        metadata_uri: String,
+        gossip_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+        let clock = Clock::get()?;
+
+        require!(!protocol_state.paused, SynapseError::ProtocolPaused);
+
+        // Transfer stake tokens; fee-bearing mints deliver less than sent
+        let staked = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.staker_token_account,
+            &ctx.accounts.stake_mint,
+            &mut ctx.accounts.protocol_vault,
+            &ctx.accounts.staker,
+            stake_amount,
+        )?;
+        require!(
+            staked >= protocol_state.min_stake,
+            SynapseError::InsufficientStake
+        );
+
+        // Initialize node state
+        node_state.owner = ctx.accounts.staker.key();
+        node_state.protocol_state = protocol_state.key();
+        node_state.activate(
+            protocol_state,
+            staked,
+            neural_state_root,
+            region,
+            client_version,
+        )?;
+        node_state.set_metadata(metadata_uri, gossip_pubkey)?;
+
+        emit!(NodeRegistered {
+            node: ctx.accounts.staker.key(),
+            stake: staked,
+            timestamp: clock.unix_timestamp,
+        });
+        emit!(NodeMetadataUpdated {
+            node: node_state.owner,
+            metadata_uri: node_state.metadata_uri.clone(),
+            gossip_pubkey,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // The node PDA outlives deregistration (until cleanup_inactive_nodes
+    // closes it), so returning stakers re-enter through here instead of
+    // register_node
+    pub fn reactivate_node(
+        ctx: Context<ReactivateNode>,
+        stake_amount: u64,
+        neural_state_root: [u8; 32],
+        region: u8,
+        client_version: u32,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        require!(!protocol_state.paused, SynapseError::ProtocolPaused);
+        require!(
+            !node_state.is_active && node_state.stake == 0,
+            SynapseError::NodeStillRegistered
+        );
+
+        let staked = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.staker_token_account,
+            &ctx.accounts.stake_mint,
+            &mut ctx.accounts.protocol_vault,
+            &ctx.accounts.staker,
+            stake_amount,
+        )?;
+        require!(
+            staked >= protocol_state.min_stake,
+            SynapseError::InsufficientStake
+        );
+
+        node_state.activate(
+            protocol_state,
+            staked,
+            neural_state_root,
+            region,
+            client_version,
+        )?;
+
+        emit!(NodeRegistered {
+            node: ctx.accounts.staker.key(),
+            stake: staked,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn register_observer(ctx: Context<RegisterObserver>) -> Result<()> {
+        let observer = &mut ctx.accounts.observer;
+        let now = Clock::get()?.unix_timestamp;
+
+        // Observers hold no stake and no NodeState, so they can never vote,
+        // propose or count towards active_node_count
+        observer.owner = ctx.accounts.owner.key();
+        observer.protocol_state = ctx.accounts.protocol_state.key();
+        observer.registered_at = now;
+
+        emit!(ObserverRegistered {
+            observer: observer.owner,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_neural_state(
+        ctx: Context<ProposeNeuralState>,
+        neural_state_root: [u8; 32],
+        signature: Vec<u8>,
+        computation_nonce: u64,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+        let mut proposal = ctx.accounts.proposal.load_init()?;
+        let clock = Clock::get()?;
+
+        require!(!protocol_state.paused, SynapseError::ProtocolPaused);
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+        require!(!node_state.is_paused, SynapseError::NodePaused);
+        // The proposer auto-votes below, and a delegating node's weight is
+        // already cast by its delegate
+        require!(node_state.vote_delegate.is_none(), SynapseError::VoteDelegated);
+        // The proposal account is created either way, so an understaked
+        // proposer is rejected here; heartbeat or voting deactivates it
+        require!(
+            node_state.stake >= protocol_state.min_stake,
+            SynapseError::InsufficientStake
+        );
+        require!(
+            node_state.reputation >= protocol_state.min_proposer_reputation
+                && node_state.stake >= protocol_state.min_proposer_stake,
+            SynapseError::NotEligibleToPropose
+        );
+        require!(
+            clock.unix_timestamp
+                >= node_state
+                    .last_proposal_at
+                    .checked_add(protocol_state.proposal_cooldown)
+                    .ok_or(SynapseError::MathOverflow)?,
+            SynapseError::ProposalCooldown
+        );
+        require!(
+            node_state.client_version >= protocol_state.min_client_version,
+            SynapseError::ClientVersionTooOld
+        );
+        require!(
+            !protocol_state.in_sync_window(clock.unix_timestamp),
+            SynapseError::InSyncWindow
+        );
+        require!(
+            verify_signature(
+                &neural_state_root,
+                &signature,
+                &ctx.accounts.staker.key(),
+                &ctx.accounts.instructions,
+            ),
+            SynapseError::InvalidSignature
+        );
+        require!(
+            verify_computation_proof(
+                &neural_state_root,
+                &ctx.accounts.staker.key(),
+                computation_nonce,
+                protocol_state.computation_difficulty,
+            ),
+            SynapseError::InvalidComputationProof
+        );
+
+        let proposal_id = protocol_state.proposal_count;
+        protocol_state.proposal_count = protocol_state
+            .proposal_count
+            .checked_add(1)
+            .ok_or(SynapseError::MathOverflow)?;
+
+        // load_init leaves every other field zeroed
+        proposal.proposal_id = proposal_id;
+        proposal.protocol_state = protocol_state.key();
+        proposal.neural_state_root = neural_state_root;
+        proposal.timestamp = clock.unix_timestamp;
+        // A zero TTL leaves proposals open indefinitely
+        proposal.expires_at = if protocol_state.proposal_ttl == 0 {
+            i64::MAX
+        } else {
+            clock
+                .unix_timestamp
+                .checked_add(protocol_state.proposal_ttl)
+                .ok_or(SynapseError::MathOverflow)?
+        };
+        proposal.proposer = ctx.accounts.staker.key();
+        protocol_state.claim_pending_root(PendingRoot {
+            neural_state_root,
+            proposal: ctx.accounts.proposal.key(),
+            created_at: clock.unix_timestamp,
+            expires_at: proposal.expires_at,
+        })?;
+        mark_region(&mut proposal.approval_regions, node_state.region);
+        proposal.snapshot_node_count = protocol_state.active_node_count;
+        proposal.snapshot_total_stake = protocol_state.total_active_stake;
+
+        // Auto-vote by proposer
+        proposal.record_vote(
+            ctx.accounts.staker.key(),
+            node_state.voting_weight(protocol_state),
+        )?;
+        if node_state.take_reputation_vote(protocol_state, clock.unix_timestamp) {
+            proposal.mark_reputation_eligible();
+        }
+        node_state.last_proposal_at = clock.unix_timestamp;
+
+        emit!(ProposalCreated {
+            proposal_id,
+            neural_state_root,
+            proposer: ctx.accounts.staker.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn commit_and_propose(
+        ctx: Context<ProposeNeuralState>,
+        neural_state_root: [u8; 32],
+        signature: Vec<u8>,
+        computation_nonce: u64,
+    ) -> Result<()> {
+        let node_state = &mut ctx.accounts.node_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        node_state.neural_state_root = neural_state_root;
+        node_state.record_update(ctx.accounts.protocol_state.epoch, now);
+
+        emit!(NeuralStateUpdated {
+            node: ctx.accounts.staker.key(),
+            neural_state_root,
+            timestamp: now,
+        });
+
+        // The proposer's signature over the root covers both the local commit
+        // and the proposal, and any failed check reverts the commit as well
+        propose_neural_state(ctx, neural_state_root, signature, computation_nonce)
+    }
+
+    pub fn vote_on_proposal(ctx: Context<VoteOnProposal>, support: bool) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+        let proposal_key = ctx.accounts.proposal.key();
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+
+        require!(!protocol_state.paused, SynapseError::ProtocolPaused);
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+        require!(!node_state.is_paused, SynapseError::NodePaused);
+        // A delegating node's weight is cast by its delegate; it must clear
+        // vote_delegate before voting directly again
+        require!(node_state.vote_delegate.is_none(), SynapseError::VoteDelegated);
+        // Failing here would roll back the deactivation, so the ballot is
+        // dropped and reported through VoteDropped instead
+        if deactivate_if_understaked(protocol_state, node_state)? {
+            emit!(VoteDropped {
+                proposal_id: proposal.proposal_id,
+                proposal: proposal_key,
+                node: node_state.owner,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+            return Ok(());
+        }
+        require!(proposal.executed == 0, SynapseError::ProposalAlreadyExecuted);
+        require!(proposal.rejected == 0, SynapseError::ProposalRejected);
+        let now = Clock::get()?.unix_timestamp;
+        require!(!proposal.is_expired(now), SynapseError::ProposalExpired);
+
+        proposal.record_ballot(
+            ctx.accounts.voter.key(),
+            node_state.voting_weight(protocol_state),
+            node_state.region,
+            support,
+        )?;
+        // Approvals always count toward consensus, but only spaced ones earn
+        // reputation. Delegated ballots follow the voter's spacing.
+        let earns_reputation = support && node_state.take_reputation_vote(protocol_state, now);
+        if earns_reputation {
+            proposal.mark_reputation_eligible();
+        }
+
+        // Count nodes delegating to this voter, directly or through a chain
+        // of delegates already counted earlier in remaining_accounts. They
+        // vote the same way as the voter.
+        let mut represented = vec![ctx.accounts.voter.key()];
+        for account_info in ctx.remaining_accounts.iter() {
+            let delegator = node_account(account_info, protocol_state.key())?;
+
+            require!(delegator.is_active, SynapseError::NodeNotActive);
+            require!(!delegator.is_paused, SynapseError::NodePaused);
+            require!(
+                delegator
+                    .vote_delegate
+                    .map_or(false, |delegate| represented.contains(&delegate)),
+                SynapseError::InvalidDelegation
+            );
+
+            proposal.record_ballot(
+                delegator.owner,
+                delegator.voting_weight(protocol_state),
+                delegator.region,
+                support,
+            )?;
+            if earns_reputation {
+                proposal.mark_reputation_eligible();
+            }
+            represented.push(delegator.owner);
+        }
+
+        if support {
+            check_consensus(
+                proposal_key,
+                &mut proposal,
+                protocol_state,
+                &mut ctx.accounts.proposer_state,
+            )?;
+        } else {
+            check_rejection(proposal_key, &mut proposal, protocol_state)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn reevaluate_proposal(ctx: Context<ReevaluateProposal>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let proposal_key = ctx.accounts.proposal.key();
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+
+        require!(proposal.executed == 0, SynapseError::ProposalAlreadyExecuted);
+        require!(proposal.rejected == 0, SynapseError::ProposalRejected);
+        require!(
+            !proposal.is_expired(Clock::get()?.unix_timestamp),
+            SynapseError::ProposalExpired
+        );
+
+        // Thresholds may have moved since the last vote, so an open proposal
+        // can meet consensus (or be rejected) without a new vote
+        check_consensus(
+            proposal_key,
+            &mut proposal,
+            protocol_state,
+            &mut ctx.accounts.proposer_state,
+        )?;
+        check_rejection(proposal_key, &mut proposal, protocol_state)?;
+
+        Ok(())
+    }
+
+    pub fn expire_proposal(ctx: Context<ExpireProposal>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        // Proposals that reached consensus await execution, not expiry
+        require!(proposal.consensus_reached == 0, SynapseError::InvalidParameter);
+        require!(proposal.expired == 0, SynapseError::ProposalExpired);
+        require!(proposal.is_expired(now), SynapseError::ProposalNotExpired);
+
+        proposal.expired = 1;
+        protocol_state.release_pending_root(&ctx.accounts.proposal.key());
+
+        emit!(ProposalExpired {
+            proposal_id: proposal.proposal_id,
+            proposal: ctx.accounts.proposal.key(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(proposal.rejected == 0, SynapseError::ProposalRejected);
+        require!(proposal.consensus_reached != 0, SynapseError::ConsensusNotReached);
+        require!(proposal.executed == 0, SynapseError::ProposalAlreadyExecuted);
+        require!(proposal.challenge_ends_at == 0, SynapseError::FinalizationRequired);
+        require!(now >= proposal.executable_at, SynapseError::TimelockActive);
+
+        execute_root(
+            ctx.accounts.proposal.key(),
+            &mut proposal,
+            protocol_state,
+            &mut ctx.accounts.proposer_state,
+            now,
+        )
+    }
+
+    // Opens a dispute against a proposal inside its challenge window. The
+    // bond is held in the protocol vault and execution waits on the admin.
+    pub fn challenge_proposal(ctx: Context<ChallengeProposal>) -> Result<()> {
+        let protocol_state = &ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(proposal.rejected == 0, SynapseError::ProposalRejected);
+        require!(proposal.consensus_reached != 0, SynapseError::ConsensusNotReached);
+        require!(proposal.executed == 0, SynapseError::ProposalAlreadyExecuted);
+        require!(proposal.challenged == 0, SynapseError::ProposalUnderChallenge);
+        require!(now < proposal.challenge_ends_at, SynapseError::ChallengeWindowClosed);
+
+        // The bond recorded is what the vault received, so a refund can't
+        // pay out transfer fees the vault never held
+        let bond = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.challenger_token_account,
+            &ctx.accounts.stake_mint,
+            &mut ctx.accounts.protocol_vault,
+            &ctx.accounts.challenger,
+            protocol_state.challenge_bond,
+        )?;
+
+        proposal.challenged = 1;
+        proposal.challenger = ctx.accounts.challenger.key();
+        proposal.challenge_bond = bond;
+        proposal.challenge_resolve_by = now
+            .checked_add(protocol_state.challenge_resolution_period)
+            .ok_or(SynapseError::MathOverflow)?;
+
+        emit!(ProposalChallenged {
+            proposal_id: proposal.proposal_id,
+            proposal: ctx.accounts.proposal.key(),
+            challenger: proposal.challenger,
+            bond,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // An upheld challenge rejects the proposal and refunds the bond. A
+    // dismissed one forfeits the bond to forfeited_bonds, withdrawable by the
+    // admin, and lets the proposal be finalized once its window closes.
+    pub fn resolve_challenge(ctx: Context<ResolveChallenge>, upheld: bool) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+
+        require!(proposal.challenged != 0, SynapseError::NotChallenged);
+        require_keys_eq!(
+            ctx.accounts.challenger_token_account.owner,
+            proposal.challenger,
+            SynapseError::Unauthorized
+        );
+
+        if upheld {
+            let vault_authority_seeds = &[
+                protocol_state.to_account_info().key.as_ref(),
+                &[protocol_state.vault_authority_bump],
+            ];
+            let vault_signer = &[&vault_authority_seeds[..]];
+
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.protocol_vault.to_account_info(),
+                    mint: ctx.accounts.stake_mint.to_account_info(),
+                    to: ctx.accounts.challenger_token_account.to_account_info(),
+                    authority: ctx.accounts.vault_authority.to_account_info(),
+                },
+                vault_signer,
+            );
+            token_interface::transfer_checked(
+                transfer_ctx,
+                proposal.challenge_bond,
+                ctx.accounts.stake_mint.decimals,
+            )?;
+            proposal.rejected = 1;
+            protocol_state.release_pending_root(&ctx.accounts.proposal.key());
+
+            emit!(ProposalRejected {
+                proposal_id: proposal.proposal_id,
+                proposal: ctx.accounts.proposal.key(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        } else {
+            protocol_state.forfeited_bonds = protocol_state
+                .forfeited_bonds
+                .checked_add(proposal.challenge_bond)
+                .ok_or(SynapseError::MathOverflow)?;
+        }
+        proposal.challenged = 0;
+
+        emit!(ChallengeResolved {
+            proposal_id: proposal.proposal_id,
+            proposal: ctx.accounts.proposal.key(),
+            challenger: proposal.challenger,
+            upheld,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Dismisses a challenge the admin left unresolved past its deadline. The
+    // bond is forfeited as on a dismissal, and the proposal can be finalized.
+    pub fn expire_challenge(ctx: Context<ExpireChallenge>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(proposal.challenged != 0, SynapseError::NotChallenged);
+        require!(
+            now >= proposal.challenge_resolve_by,
+            SynapseError::ChallengeResolutionPending
+        );
+
+        protocol_state.forfeited_bonds = protocol_state
+            .forfeited_bonds
+            .checked_add(proposal.challenge_bond)
+            .ok_or(SynapseError::MathOverflow)?;
+        proposal.challenged = 0;
+
+        emit!(ChallengeExpired {
+            proposal_id: proposal.proposal_id,
+            proposal: ctx.accounts.proposal.key(),
+            challenger: proposal.challenger,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Pays forfeited challenge bonds out of the vault. Staked tokens can't be
+    // withdrawn this way; the amount is capped by forfeited_bonds.
+    pub fn withdraw_forfeited_bonds(
+        ctx: Context<WithdrawForfeitedBonds>,
+        amount: u64,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+
+        require!(amount > 0, SynapseError::InvalidParameter);
+        protocol_state.forfeited_bonds = protocol_state
+            .forfeited_bonds
+            .checked_sub(amount)
+            .ok_or(SynapseError::InsufficientForfeitedBonds)?;
+
+        let vault_authority_seeds = &[
+            protocol_state.to_account_info().key.as_ref(),
+            &[protocol_state.vault_authority_bump],
+        ];
+        let vault_signer = &[&vault_authority_seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.protocol_vault.to_account_info(),
+                mint: ctx.accounts.stake_mint.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault_signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.stake_mint.decimals)?;
+
+        emit!(ForfeitedBondsWithdrawn {
+            recipient: ctx.accounts.recipient_token_account.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Executes a proposal whose challenge window closed without an open dispute
+    pub fn finalize_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(proposal.rejected == 0, SynapseError::ProposalRejected);
+        require!(proposal.consensus_reached != 0, SynapseError::ConsensusNotReached);
+        require!(proposal.executed == 0, SynapseError::ProposalAlreadyExecuted);
+        require!(proposal.challenged == 0, SynapseError::ProposalUnderChallenge);
+        require!(now >= proposal.challenge_ends_at, SynapseError::ChallengeWindowOpen);
+        require!(now >= proposal.executable_at, SynapseError::TimelockActive);
+
+        emit!(ProposalFinalized {
+            proposal_id: proposal.proposal_id,
+            proposal: ctx.accounts.proposal.key(),
+            timestamp: now,
+        });
+
+        execute_root(
+            ctx.accounts.proposal.key(),
+            &mut proposal,
+            protocol_state,
+            &mut ctx.accounts.proposer_state,
+            now,
+        )
+    }
+
+    pub fn update_reputations(ctx: Context<UpdateReputations>) -> Result<()> {
+        let protocol_state = &ctx.accounts.protocol_state;
+        let mut proposal = ctx.accounts.proposal.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(proposal.executed != 0, SynapseError::ProposalNotExecuted);
+        require!(
+            proposal.participation_bps >= protocol_state.reputation_participation_floor,
+            SynapseError::ParticipationBelowFloor
+        );
+
+        // Nodes that approved the accepted root gain reputation; absentees are
+        // left untouched. Each voter's bit in reputation_settled prevents the
+        // same vote from being credited twice across paginated calls.
+        for account_info in ctx.remaining_accounts.iter() {
+            let mut node = node_account(account_info, protocol_state.key())?;
+            let index = match proposal.voters().iter().position(|voter| *voter == node.owner) {
+                Some(index) => index,
+                None => continue,
+            };
+            if bit_is_set(&proposal.reputation_settled, index) {
+                continue;
+            }
+            set_bit(&mut proposal.reputation_settled, index);
+            if !bit_is_set(&proposal.reputation_eligible, index) {
+                node.exit(ctx.program_id)?;
+                continue;
+            }
+
+            let old_reputation = node.reputation;
+            node.reputation = node
+                .reputation
+                .checked_add(protocol_state.reputation_reward)
+                .ok_or(SynapseError::MathOverflow)?;
+            node.exit(ctx.program_id)?;
+
+            emit!(ReputationUpdated {
+                node: node.owner,
+                old_reputation,
+                new_reputation: node.reputation,
+                timestamp: now,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn set_vote_delegate(
+        ctx: Context<SetVoteDelegate>,
+        delegate: Option<Pubkey>,
+    ) -> Result<()> {
+        let node_state = &mut ctx.accounts.node_state;
+        let owner = ctx.accounts.owner.key();
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+
+        if let Some(delegate) = delegate {
+            require!(delegate != owner, SynapseError::SelfDelegation);
+
+            // remaining_accounts must walk the delegate's chain to its end:
+            // the delegate's node, then its delegate's node, and so on
+            require!(
+                ctx.remaining_accounts.len() <= MAX_DELEGATION_DEPTH,
+                SynapseError::DelegationChainTooLong
+            );
+            let mut current = delegate;
+            let mut chain_complete = false;
+            for account_info in ctx.remaining_accounts.iter() {
+                let link = node_account(account_info, node_state.protocol_state)?;
+                require!(link.owner == current, SynapseError::InvalidDelegation);
+                require!(link.is_active, SynapseError::NodeNotActive);
+
+                match link.vote_delegate {
+                    Some(next) => {
+                        require!(next != owner, SynapseError::DelegationCycle);
+                        current = next;
+                    }
+                    None => {
+                        chain_complete = true;
+                        break;
+                    }
+                }
+            }
+            require!(chain_complete, SynapseError::InvalidDelegation);
+        }
+
+        node_state.vote_delegate = delegate;
+
+        emit!(VoteDelegateSet {
+            node: owner,
+            delegate,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        let next_epoch_at = protocol_state
+            .last_epoch_update
+            .checked_add(protocol_state.epoch_duration)
+            .ok_or(SynapseError::MathOverflow)?;
+        require!(now >= next_epoch_at, SynapseError::EpochNotElapsed);
+
+        protocol_state.epoch = protocol_state
+            .epoch
+            .checked_add(1)
+            .ok_or(SynapseError::MathOverflow)?;
+        protocol_state.last_epoch_update = now;
+
+        // Periodic per-epoch work (reputation decay, reward accrual) hooks in here
+
+        // Per-node upkeep for the active, unpaused nodes passed in
+        // remaining_accounts, each handled at most once. Both checks work from
+        // the epochs recorded on the node, so one left out catches up the next
+        // time it is passed.
+        let mut seen = Vec::with_capacity(ctx.remaining_accounts.len());
+        for account_info in ctx.remaining_accounts.iter() {
+            if seen.contains(account_info.key) {
+                continue;
+            }
+            seen.push(*account_info.key);
+
+            let mut node = node_account(account_info, protocol_state.key())?;
+            if !node.is_active || node.is_paused {
+                continue;
+            }
+
+            let old_reputation = node.reputation;
+            decay_reputation(protocol_state, &mut node);
+            if node.reputation != old_reputation {
+                emit!(ReputationUpdated {
+                    node: node.owner,
+                    old_reputation,
+                    new_reputation: node.reputation,
+                    timestamp: now,
+                });
+            }
+
+            // Missed epochs are derived from the last heartbeat. Lapsed nodes
+            // are paused rather than deregistered so their owners can still
+            // resume or withdraw.
+            if protocol_state.max_missed_epochs > 0 {
+                node.missed_epochs = protocol_state
+                    .epoch
+                    .saturating_sub(node.last_heartbeat_epoch)
+                    .saturating_sub(1);
+                if node.missed_epochs >= protocol_state.max_missed_epochs {
+                    node.settle_rewards(protocol_state)?;
+                    node.is_paused = true;
+                    protocol_state.active_node_count = protocol_state
+                        .active_node_count
+                        .checked_sub(1)
+                        .ok_or(SynapseError::MathOverflow)?;
+                    protocol_state.remove_active_stake(node.stake)?;
+                    node.sync_reward_debt(protocol_state);
+
+                    emit!(NodeAutoDeactivated {
+                        node: node.owner,
+                        missed_epochs: node.missed_epochs,
+                        timestamp: now,
+                    });
+                }
+            }
+            node.exit(ctx.program_id)?;
+        }
+
+        emit!(EpochAdvanced {
+            epoch: protocol_state.epoch,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    // Rewards each epoch once. Touching every node in one transaction isn't
+    // feasible, so this only grows the reward-per-stake accumulator and nodes
+    // pull their share with claim_reward.
+    pub fn distribute_rewards(ctx: Context<DistributeRewards>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let amount = protocol_state.epoch_reward;
+
+        require!(
+            protocol_state.epoch > protocol_state.last_rewarded_epoch,
+            SynapseError::EpochAlreadyRewarded
+        );
+        require!(amount > 0, SynapseError::InvalidParameter);
+        require!(
+            protocol_state.total_active_stake > 0,
+            SynapseError::NoActiveStake
+        );
+
+        let reward_liabilities = protocol_state
+            .reward_liabilities
+            .checked_add(amount)
+            .ok_or(SynapseError::MathOverflow)?;
+        require!(
+            ctx.accounts.reward_pool.amount >= reward_liabilities,
+            SynapseError::VaultInsufficientBalance
+        );
+
+        protocol_state.reward_per_stake = protocol_state
+            .reward_per_stake
+            .checked_add(
+                amount as u128 * REWARD_PRECISION / protocol_state.total_active_stake as u128,
+            )
+            .ok_or(SynapseError::MathOverflow)?;
+        protocol_state.reward_liabilities = reward_liabilities;
+        protocol_state.last_rewarded_epoch = protocol_state.epoch;
+
+        emit!(RewardDistributed {
+            epoch: protocol_state.epoch,
+            amount,
+            total_active_stake: protocol_state.total_active_stake,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        node_state.settle_rewards(protocol_state)?;
+        let amount = node_state.accrued_rewards;
+        require!(amount > 0, SynapseError::NothingToClaim);
+
+        let vault_authority_seeds = &[
+            protocol_state.to_account_info().key.as_ref(),
+            &[protocol_state.vault_authority_bump],
+        ];
+        let vault_signer = &[&vault_authority_seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.reward_pool.to_account_info(),
+                mint: ctx.accounts.reward_mint.to_account_info(),
+                to: ctx.accounts.staker_token_account.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault_signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.reward_mint.decimals)?;
+
+        node_state.accrued_rewards = 0;
+        // Rounding can leave a claim a unit above the tracked liability
+        protocol_state.reward_liabilities =
+            protocol_state.reward_liabilities.saturating_sub(amount);
+
+        emit!(RewardClaimed {
+            node: ctx.accounts.staker.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn heartbeat(ctx: Context<Heartbeat>, client_version: u32) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+        // The NodeDeactivated event tells the client this heartbeat wasn't
+        // recorded; erroring would roll the deactivation back
+        if deactivate_if_understaked(protocol_state, node_state)? {
+            return Ok(());
+        }
+
+        node_state.last_heartbeat_epoch = protocol_state.epoch;
+        node_state.missed_epochs = 0;
+        node_state.client_version = client_version;
+        node_state.record_update(protocol_state.epoch, Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    pub fn update_neural_state(
+        ctx: Context<UpdateNeuralState>,
+        neural_state_root: [u8; 32],
+    ) -> Result<()> {
+        let node_state = &mut ctx.accounts.node_state;
+        let clock = Clock::get()?;
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+
+        node_state.neural_state_root = neural_state_root;
+        node_state.record_update(ctx.accounts.protocol_state.epoch, clock.unix_timestamp);
+
+        emit!(NeuralStateUpdated {
+            node: ctx.accounts.owner.key(),
+            neural_state_root,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_node_metadata(
+        ctx: Context<UpdateNodeMetadata>,
+        metadata_uri: String,
+        gossip_pubkey: [u8; 32],
+    ) -> Result<()> {
+        let node_state = &mut ctx.accounts.node_state;
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+        node_state.set_metadata(metadata_uri, gossip_pubkey)?;
+
+        emit!(NodeMetadataUpdated {
+            node: node_state.owner,
+            metadata_uri: node_state.metadata_uri.clone(),
+            gossip_pubkey,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Checks that `leaf` sits at `index` in the tree committed as the node's
+    // neural_state_root; see compute_merkle_root for the hashing scheme
+    pub fn verify_inclusion(
+        ctx: Context<VerifyInclusion>,
+        leaf: [u8; 32],
+        index: u32,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require!(proof.len() <= MAX_MERKLE_DEPTH, SynapseError::ProofInvalid);
+        require!(
+            proof.len() == MAX_MERKLE_DEPTH || (index as u64) < 1u64 << proof.len(),
+            SynapseError::ProofInvalid
+        );
+        require!(
+            compute_merkle_root(&leaf, index, &proof) == ctx.accounts.node_state.neural_state_root,
+            SynapseError::ProofInvalid
+        );
+
+        Ok(())
+    }
+
+    pub fn emit_stake_distribution(ctx: Context<EmitStakeDistribution>) -> Result<()> {
+        require_distinct_accounts(ctx.remaining_accounts)?;
+        let mut stakes = Vec::with_capacity(ctx.remaining_accounts.len());
+        for account_info in ctx.remaining_accounts.iter() {
+            let node = node_account(account_info, ctx.accounts.protocol_state.key())?;
+            if node.is_active {
+                stakes.push(node.stake);
+            }
+        }
+
+        let total_stake: u128 = stakes.iter().map(|stake| *stake as u128).sum();
+
+        emit!(StakeDistribution {
+            node_count: stakes.len() as u64,
+            total_stake: total_stake as u64,
+            gini_bps: gini_coefficient_bps(&mut stakes),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn emit_protocol_tvl(ctx: Context<EmitStakeDistribution>) -> Result<()> {
+        let protocol_state = &ctx.accounts.protocol_state;
+
+        emit!(ProtocolTvl {
+            total_staked: protocol_state.total_staked,
+            total_active_stake: protocol_state.total_active_stake,
+            active_node_count: protocol_state.active_node_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn emit_decentralization_score(ctx: Context<EmitStakeDistribution>) -> Result<()> {
+        require_distinct_accounts(ctx.remaining_accounts)?;
+        let mut stakes = Vec::with_capacity(ctx.remaining_accounts.len());
+        let mut regions = [0u8; 32];
+        for account_info in ctx.remaining_accounts.iter() {
+            let node = node_account(account_info, ctx.accounts.protocol_state.key())?;
+            if node.is_active {
+                stakes.push(node.stake);
+                mark_region(&mut regions, node.region);
+            }
+        }
+
+        let node_count = stakes.len() as u64;
+        let gini_bps = gini_coefficient_bps(&mut stakes);
+        let distinct_regions = count_regions(&regions) as u64;
+
+        // Equal-weight average of three components, each in basis points:
+        // stake equality (1 - Gini), the share of nodes in distinct regions,
+        // and node count relative to DECENTRALIZATION_TARGET_NODES
+        let decentralization_score = if node_count == 0 {
+            0
+        } else {
+            let stake_score = BPS_DENOMINATOR - gini_bps;
+            let region_score = distinct_regions * BPS_DENOMINATOR / node_count;
+            let size_score = node_count.min(DECENTRALIZATION_TARGET_NODES) * BPS_DENOMINATOR
+                / DECENTRALIZATION_TARGET_NODES;
+            (stake_score + region_score + size_score) / 3
+        };
+
+        emit!(DecentralizationScore {
+            node_count,
+            gini_bps,
+            distinct_regions,
+            decentralization_score,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_node_paused(ctx: Context<SetNodePaused>, paused: bool) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+        require!(node_state.is_paused != paused, SynapseError::InvalidParameter);
+        node_state.settle_rewards(protocol_state)?;
+
+        // Paused nodes keep their stake but leave the consensus denominator
+        protocol_state.active_node_count = if paused {
+            protocol_state.active_node_count.checked_sub(1)
+        } else {
+            protocol_state.active_node_count.checked_add(1)
+        }
+        .ok_or(SynapseError::MathOverflow)?;
+        if paused {
+            protocol_state.remove_active_stake(node_state.stake)?;
+        } else {
+            protocol_state.require_voter_capacity()?;
+            protocol_state.add_active_stake(node_state.stake)?;
+        }
+        node_state.is_paused = paused;
+        node_state.sync_reward_debt(protocol_state);
+        if !paused {
+            // Resuming counts as a heartbeat so liveness checks start afresh
+            node_state.last_heartbeat_epoch = protocol_state.epoch;
+            node_state.missed_epochs = 0;
+        }
+
+        emit!(NodePauseSet {
+            node: ctx.accounts.owner.key(),
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_stake(
+        ctx: Context<UpdateStake>,
+        amount: u64,
+        increase: bool,
+    ) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+
+        if increase {
+            require!(!protocol_state.paused, SynapseError::ProtocolPaused);
+
+            // Transfer additional stake
+            let received = deposit_to_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.staker_token_account,
+                &ctx.accounts.stake_mint,
+                &mut ctx.accounts.protocol_vault,
+                &ctx.accounts.staker,
+                amount,
+            )?;
+            node_state.settle_rewards(protocol_state)?;
+            node_state.stake = node_state
+                .stake
+                .checked_add(received)
+                .ok_or(SynapseError::MathOverflow)?;
+            protocol_state.add_staked(received)?;
+            if !node_state.is_paused {
+                protocol_state.add_active_stake(received)?;
+            }
+            node_state.sync_reward_debt(protocol_state);
+
+            emit!(StakeIncreased {
+                node: ctx.accounts.staker.key(),
+                amount: received,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        } else {
+            queue_unstake(protocol_state, node_state, amount)?;
+
+            emit!(StakeDecreased {
+                node: ctx.accounts.staker.key(),
+                amount,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn request_unstake(ctx: Context<RequestUnstake>, amount: u64) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        require!(node_state.is_active, SynapseError::NodeNotActive);
+
+        queue_unstake(protocol_state, node_state, amount)?;
+
+        emit!(UnstakeRequested {
+            node: ctx.accounts.staker.key(),
+            amount,
+            available_at: node_state.unstake_available_at,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_unstake(ctx: Context<ClaimUnstake>) -> Result<()> {
+        let protocol_state = &ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+        let amount = node_state.pending_unstake;
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(amount > 0, SynapseError::NothingToClaim);
+        require!(
+            now >= node_state.unstake_available_at,
+            SynapseError::CooldownNotElapsed
+        );
+        require!(
+            ctx.accounts.protocol_vault.amount >= amount,
+            SynapseError::VaultInsufficientBalance
+        );
+
+        // Return stake to user
+        let vault_authority_seeds = &[
+            protocol_state.to_account_info().key.as_ref(),
+            &[protocol_state.vault_authority_bump],
+        ];
+        let vault_signer = &[&vault_authority_seeds[..]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.protocol_vault.to_account_info(),
+                mint: ctx.accounts.stake_mint.to_account_info(),
+                to: ctx.accounts.staker_token_account.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault_signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.stake_mint.decimals)?;
+
+        node_state.pending_unstake = 0;
+
+        emit!(UnstakeClaimed {
+            node: ctx.accounts.staker.key(),
+            amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn deregister_node(ctx: Context<DeregisterNode>) -> Result<()> {
+        let protocol_state = &mut ctx.accounts.protocol_state;
+        let node_state = &mut ctx.accounts.node_state;
+
+        // Nodes deactivated with stake left (e.g. under a raised min_stake)
+        // can still deregister to withdraw it
+        require!(
+            node_state.is_active || node_state.stake > 0,
+            SynapseError::NodeNotActive
+        );
+        require!(
+            protocol_state.epoch
+                >= node_state
+                    .registered_epoch
+                    .checked_add(protocol_state.min_active_epochs)
+                    .ok_or(SynapseError::MathOverflow)?,
+            SynapseError::MinActivePeriodNotMet
+        );
+
+        node_state.settle_rewards(protocol_state)?;
+
+        // The full stake becomes claimable after the unstake cooldown
+        let stake = node_state.stake;
+        node_state.pending_unstake = node_state
+            .pending_unstake
+            .checked_add(stake)
+            .ok_or(SynapseError::MathOverflow)?;
+        node_state.unstake_available_at = Clock::get()?
+            .unix_timestamp
+            .checked_add(protocol_state.unstake_cooldown)
+            .ok_or(SynapseError::MathOverflow)?;
+
+        // Paused and deactivated nodes were already removed from the active count
+        if node_state.is_active && !node_state.is_paused {
+            protocol_state.active_node_count = protocol_state
+                .active_node_count
+                .checked_sub(1)
+                .ok_or(SynapseError::MathOverflow)?;
+            protocol_state.remove_active_stake(stake)?;
+        }
+        protocol_state.remove_staked(stake)?;
+        node_state.is_active = false;
+        node_state.is_paused = false;
+        node_state.stake = 0;
+        node_state.sync_reward_debt(protocol_state);
+        node_state.record_update(protocol_state.epoch, Clock::get()?.unix_timestamp);
+
+        emit!(NodeDeregistered {
+            node: ctx.accounts.staker.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(init, payer = admin, space = 8 + ProtocolState::LEN)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminOnly<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolVault<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = protocol_vault.owner == vault_authority.key() @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that owns the protocol vault
+    #[account(seeds = [protocol_state.key().as_ref()], bump)]
+    pub vault_authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRewardParams<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = reward_pool.owner == vault_authority.key() @ SynapseError::InvalidVault,
+        constraint = reward_pool.key() != protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub reward_pool: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that owns the reward pool
+    #[account(seeds = [protocol_state.key().as_ref()], bump)]
+    pub vault_authority: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CleanupInactiveNodes<'info> {
+    #[account(has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlashNode<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub node_state: Account<'info, NodeState>,
+    #[account(has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: Option<AccountLoader<'info, Proposal>>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeSlash<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub slashed_node: Account<'info, NodeState>,
+    #[account(has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterNode<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        init,
+        payer = staker,
+        space = 8 + NodeState::LEN,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump
+    )]
+    pub node_state: Account<'info, NodeState>,
+    #[account(mut)]
+    pub staker: Signer<'info>,
+    #[account(
+        mut,
+        constraint = staker_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub staker_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStake<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub staker: Signer<'info>,
+    #[account(
+        mut,
+        constraint = staker_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub staker_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ReactivateNode<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    #[account(mut)]
+    pub staker: Signer<'info>,
+    #[account(
+        mut,
+        constraint = staker_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub staker_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterObserver<'info> {
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Observer::LEN,
+        seeds = [b"observer", protocol_state.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub observer: Account<'info, Observer>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub staker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimUnstake<'info> {
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub staker: Signer<'info>,
+    #[account(
+        mut,
+        constraint = staker_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub staker_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signing for the protocol vault
+    #[account(
+        seeds = [protocol_state.key().as_ref()],
+        bump = protocol_state.vault_authority_bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct DeregisterNode<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub staker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeNeuralState<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    // Past the 10 KiB an instruction may allocate, so the client creates the
+    // account (8 + Proposal::LEN bytes, owned by this program) beforehand
+    #[account(zero)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    #[account(mut)]
+    pub staker: Signer<'info>,
+    /// CHECK: address is constrained to the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VoteOnProposal<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), voter.key().as_ref()],
+        bump,
+        constraint = node_state.owner == voter.key() @ SynapseError::Unauthorized,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    // Credited with the success if this call executes the proposal
+    #[account(
+        mut,
+        has_one = protocol_state @ SynapseError::InvalidParameter,
+        constraint = proposer_state.owner == proposal.load()?.proposer
+            @ SynapseError::InvalidParameter,
+        constraint = proposer_state.key() != node_state.key() @ SynapseError::AlreadyVoted
+    )]
+    pub proposer_state: Account<'info, NodeState>,
+    pub voter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireProposal<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct ReevaluateProposal<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    // Credited with the success if this call executes the proposal
+    #[account(
+        mut,
+        has_one = protocol_state @ SynapseError::InvalidParameter,
+        constraint = proposer_state.owner == proposal.load()?.proposer
+            @ SynapseError::InvalidParameter
+    )]
+    pub proposer_state: Account<'info, NodeState>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReputations<'info> {
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    // Credited with the success if this call executes the proposal
+    #[account(
+        mut,
+        has_one = protocol_state @ SynapseError::InvalidParameter,
+        constraint = proposer_state.owner == proposal.load()?.proposer
+            @ SynapseError::InvalidParameter
+    )]
+    pub proposer_state: Account<'info, NodeState>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeProposal<'info> {
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    pub challenger: Signer<'info>,
+    #[account(
+        mut,
+        constraint = challenger_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub challenger_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveChallenge<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = challenger_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub challenger_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signing for the protocol vault
+    #[account(
+        seeds = [protocol_state.key().as_ref()],
+        bump = protocol_state.vault_authority_bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireChallenge<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(mut, has_one = protocol_state @ SynapseError::InvalidParameter)]
+    pub proposal: AccountLoader<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawForfeitedBonds<'info> {
+    #[account(mut, has_one = admin @ SynapseError::Unauthorized)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == protocol_state.stake_mint
+            @ SynapseError::InvalidMint
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.protocol_vault @ SynapseError::InvalidVault
+    )]
+    pub protocol_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA signing for the protocol vault
+    #[account(
+        seeds = [protocol_state.key().as_ref()],
+        bump = protocol_state.vault_authority_bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(address = protocol_state.stake_mint @ SynapseError::InvalidMint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetVoteDelegate<'info> {
+    #[account(
+        mut,
+        seeds = [b"node", node_state.protocol_state.as_ref(), owner.key().as_ref()],
+        bump,
+        has_one = owner
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AdvanceEpoch<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeRewards<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(address = protocol_state.reward_pool @ SynapseError::InvalidParameter)]
+    pub reward_pool: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReward<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), staker.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub staker: Signer<'info>,
+    #[account(mut)]
+    pub staker_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        address = protocol_state.reward_pool @ SynapseError::InvalidParameter
+    )]
+    pub reward_pool: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = reward_pool.mint @ SynapseError::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA signing for the reward pool
+    #[account(
+        seeds = [protocol_state.key().as_ref()],
+        bump = protocol_state.vault_authority_bump
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), owner.key().as_ref()],
+        bump,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateNeuralState<'info> {
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), owner.key().as_ref()],
+        bump,
+        has_one = owner,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateNodeMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [b"node", node_state.protocol_state.as_ref(), owner.key().as_ref()],
+        bump,
+        has_one = owner
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyInclusion<'info> {
+    #[account(
+        seeds = [b"node", node_state.protocol_state.as_ref(), node_state.owner.as_ref()],
+        bump
+    )]
+    pub node_state: Account<'info, NodeState>,
+}
+
+#[derive(Accounts)]
+pub struct EmitStakeDistribution<'info> {
+    pub protocol_state: Account<'info, ProtocolState>,
+}
+
+#[derive(Accounts)]
+pub struct SetNodePaused<'info> {
+    #[account(mut)]
+    pub protocol_state: Account<'info, ProtocolState>,
+    #[account(
+        mut,
+        seeds = [b"node", protocol_state.key().as_ref(), owner.key().as_ref()],
+        bump,
+        has_one = owner,
+        has_one = protocol_state @ SynapseError::InvalidParameter
+    )]
+    pub node_state: Account<'info, NodeState>,
+    pub owner: Signer<'info>,
+}
+
+#[account]
+pub struct ProtocolState {
+    pub admin: Pubkey,
+    pub min_stake: u64,
+    pub epoch_duration: i64,
+    pub active_node_count: u64,
+    pub proposal_count: u64,
+    pub last_epoch_update: i64,
+    pub vault_authority_bump: u8,
+    pub sync_window: i64,
+    pub execution_delay: i64,
+    pub min_active_epochs: u64,
+    pub min_distinct_regions: u8,
+    pub computation_difficulty: u8,
+    pub cleanup_delay: i64,
+    pub require_independent_vote: bool,
+    pub reputation_reward: u64,
+    pub slash_bps: u64,
+    pub slash_reputation_penalty: u64,
+    pub epoch: u64,
+    pub unstake_cooldown: i64,
+    pub max_missed_epochs: u64,
+    pub total_active_stake: u64,
+    pub stake_weighted: bool,
+    pub recent_proposer_epochs: u64,
+    pub recent_proposer_penalty_bps: u64,
+    pub proposal_ttl: i64,
+    pub min_client_version: u32,
+    pub rejection_threshold_bps: u64,
+    pub pending_admin: Option<Pubkey>,
+    pub paused: bool,
+    pub reputation_participation_floor: u64,
+    pub consensus_threshold_bps: u64,
+    pub reputation_decay_bps: u64,
+    pub reputation_floor: u64,
+    pub reward_pool: Pubkey,
+    pub epoch_reward: u64,
+    // Cumulative reward per staked token, scaled by REWARD_PRECISION
+    pub reward_per_stake: u128,
+    pub last_rewarded_epoch: u64,
+    // Rewards distributed but not yet claimed
+    pub reward_liabilities: u64,
+    // Seconds a node's approvals must be spaced apart to earn reputation
+    pub min_reputation_vote_interval: i64,
+    pub slash_redistribution_bps: u64,
+    // Extra bar for proposing on top of min_stake; zero admits every node
+    pub min_proposer_reputation: u64,
+    pub min_proposer_stake: u64,
+    pub proposal_cooldown: i64,
+    // Ring buffer of executed roots; slot checkpoint_count % MAX_CHECKPOINTS
+    // is written next
+    pub checkpoints: [Checkpoint; MAX_CHECKPOINTS],
+    pub checkpoint_count: u64,
+    // Fixed by set_protocol_vault; staking is closed until then
+    pub stake_mint: Pubkey,
+    pub protocol_vault: Pubkey,
+    // Roots of open proposals, so a root can't be proposed twice at once
+    pub pending_roots: [PendingRoot; MAX_PENDING_ROOTS],
+    pub total_staked: u64,
+    pub challenge_period: i64,
+    pub challenge_bond: u64,
+    pub forfeited_bonds: u64,
+    pub challenge_resolution_period: i64,
+}
+
+#[account]
+pub struct NodeState {
+    pub owner: Pubkey,
+    pub stake: u64,
+    pub reputation: u64,
+    pub last_update: i64,
+    pub neural_state_root: [u8; 32],
+    pub is_active: bool,
+    pub vote_delegate: Option<Pubkey>,
+    pub registered_epoch: u64,
+    pub region: u8,
+    pub is_paused: bool,
+    pub pending_unstake: u64,
+    pub unstake_available_at: i64,
+    pub last_heartbeat_epoch: u64,
+    pub missed_epochs: u64,
+    pub last_successful_proposal_epoch: Option<u64>,
+    pub client_version: u32,
+    pub epochs_inactive: u64,
+    pub reward_debt: u128,
+    pub accrued_rewards: u64,
+    pub last_reputation_vote_at: i64,
+    // Slashed stake not yet offered to honest voters
+    pub undistributed_slash: u64,
+    pub last_proposal_at: i64,
+    // How to reach the node on the gossip layer; empty if unadvertised
+    pub metadata_uri: String,
+    pub gossip_pubkey: [u8; 32],
+    pub protocol_state: Pubkey,
+    // Proposal whose honest voters the undistributed slash is owed to
+    pub slashed_proposal: Pubkey,
+    // Part of undistributed_slash being paid out by the open round
+    pub slash_distribution_base: u64,
+    // Bit i is set once the open round has settled the winning side's voter i
+    pub slash_settled: [u8; MAX_PROPOSAL_VOTERS / 8],
+    pub last_update_epoch: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Checkpoint {
+    pub neural_state_root: [u8; 32],
+    pub epoch: u64,
+    pub timestamp: i64,
+}
+
+impl Checkpoint {
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PendingRoot {
+    pub neural_state_root: [u8; 32],
+    pub proposal: Pubkey,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+impl PendingRoot {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+
+    pub fn is_open(&self, now: i64) -> bool {
+        self.proposal != Pubkey::default() && now <= self.expires_at
+    }
+}
+
+// Zero-copy so a vote updates the account in place instead of deserializing
+// every voter. Fields are ordered to leave the repr(C) layout without padding.
+#[account(zero_copy)]
+pub struct Proposal {
+    // Sequential id from ProtocolState.proposal_count, as in ProposalCreated
+    pub proposal_id: u64,
+    pub timestamp: i64,
+    // Filled slots in voters
+    pub vote_count: u64,
+    pub executable_at: i64,
+    // Sum of the voters' stake-based voting weights
+    pub total_stake_voted: u64,
+    pub expires_at: i64,
+    // Filled slots in dissenters
+    pub no_vote_count: u64,
+    pub total_stake_against: u64,
+    pub participation_bps: u64,
+    // Active set at creation; quorum is measured against it, not live counts
+    pub snapshot_node_count: u64,
+    pub snapshot_total_stake: u64,
+    // Zero when no challenge window applies; finalize_proposal runs after it
+    pub challenge_ends_at: i64,
+    pub challenge_bond: u64,
+    // After this an open challenge can be expired by anyone, dismissing it
+    pub challenge_resolve_by: i64,
+    // Weight each ballot carried when cast, parallel to voters and
+    // dissenters; later stake changes don't reach votes already cast
+    pub voter_weights: [u64; MAX_PROPOSAL_VOTERS],
+    pub dissenter_weights: [u64; MAX_PROPOSAL_VOTERS],
+    pub neural_state_root: [u8; 32],
+    pub proposer: Pubkey,
+    pub protocol_state: Pubkey,
+    // Bitmap of regions with at least one approving node
+    pub approval_regions: [u8; 32],
+    // Bit i is set once voters[i] has been credited reputation
+    pub reputation_settled: [u8; MAX_PROPOSAL_VOTERS / 8],
+    // Bitmap over voters whose approval was spaced enough to earn reputation
+    pub reputation_eligible: [u8; MAX_PROPOSAL_VOTERS / 8],
+    pub challenger: Pubkey,
+    // Approving voters; only the first vote_count slots are set
+    pub voters: [Pubkey; MAX_PROPOSAL_VOTERS],
+    // Voters against; only the first no_vote_count slots are set
+    pub dissenters: [Pubkey; MAX_PROPOSAL_VOTERS],
+    // Flags are u8 as bool isn't Pod
+    pub executed: u8,
+    pub consensus_reached: u8,
+    pub expired: u8,
+    pub rejected: u8,
+    // Set while a dispute is open; blocks finalization
+    pub challenged: u8,
+    pub _padding: [u8; 3],
+}
+
+// Read-only participant recorded for off-chain gateway access control
+#[account]
+pub struct Observer {
+    pub owner: Pubkey,
+    pub protocol_state: Pubkey,
+    pub registered_at: i64,
+}
+
+#[error_code]
+pub enum SynapseError {
+    #[msg("Insufficient stake amount")]
+    InsufficientStake,
+    #[msg("Node is not active")]
+    NodeNotActive,
+    #[msg("Invalid signature")]
+    InvalidSignature,
+    #[msg("Proposal already executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Already voted on proposal")]
+    AlreadyVoted,
+    #[msg("Node cannot delegate to itself")]
+    SelfDelegation,
+    #[msg("Delegation would create a cycle")]
+    DelegationCycle,
+    #[msg("Delegation chain too long")]
+    DelegationChainTooLong,
+    #[msg("Invalid delegation")]
+    InvalidDelegation,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid parameter")]
+    InvalidParameter,
+    #[msg("Proposals are deferred during the epoch sync window")]
+    InSyncWindow,
+    #[msg("Proposal has not reached consensus")]
+    ConsensusNotReached,
+    #[msg("Proposal timelock has not elapsed")]
+    TimelockActive,
+    #[msg("Node has not been active for the minimum number of epochs")]
+    MinActivePeriodNotMet,
+    #[msg("Protocol vault balance is insufficient for withdrawal")]
+    VaultInsufficientBalance,
+    #[msg("Computation proof does not meet the required difficulty")]
+    InvalidComputationProof,
+    #[msg("Node is active, staked or recently deregistered")]
+    NodeNotEligibleForCleanup,
+    #[msg("Node is paused")]
+    NodePaused,
+    #[msg("Proposal has reached the maximum number of voters")]
+    ProposalFull,
+    #[msg("Arithmetic overflow or underflow")]
+    MathOverflow,
+    #[msg("Proposal has not been executed")]
+    ProposalNotExecuted,
+    #[msg("Epoch duration has not elapsed")]
+    EpochNotElapsed,
+    #[msg("Unstake cooldown has not elapsed")]
+    CooldownNotElapsed,
+    #[msg("No pending unstake to claim")]
+    NothingToClaim,
+    #[msg("Proposal has expired")]
+    ProposalExpired,
+    #[msg("Proposal has not expired yet")]
+    ProposalNotExpired,
+    #[msg("Node client version is below the required minimum")]
+    ClientVersionTooOld,
+    #[msg("Proposal was rejected")]
+    ProposalRejected,
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+    #[msg("Proposal participation is below the reputation floor")]
+    ParticipationBelowFloor,
+    #[msg("Node must be deregistered before it can be reactivated")]
+    NodeStillRegistered,
+    #[msg("Rewards for this epoch were already distributed")]
+    EpochAlreadyRewarded,
+    #[msg("No active stake to reward")]
+    NoActiveStake,
+    #[msg("Node has no slashed stake left to distribute")]
+    NoSlashToDistribute,
+    #[msg("Slashed node voted with the proposal outcome")]
+    NodeVotedWithOutcome,
+    #[msg("Proposal was neither executed nor rejected")]
+    ProposalUndecided,
+    #[msg("Node does not meet the reputation or stake required to propose")]
+    NotEligibleToPropose,
+    #[msg("Node must wait for its proposal cooldown to elapse")]
+    ProposalCooldown,
+    #[msg("Merkle proof does not match the committed neural state root")]
+    ProofInvalid,
+    #[msg("Node has delegated its vote")]
+    VoteDelegated,
+    #[msg("Metadata URI exceeds the maximum length")]
+    MetadataUriTooLong,
+    #[msg("Token account is not the protocol vault")]
+    InvalidVault,
+    #[msg("Token account mint does not match the stake mint")]
+    InvalidMint,
+    #[msg("An open proposal already carries this neural state root")]
+    DuplicateProposal,
+    #[msg("Proposal is under challenge")]
+    ProposalUnderChallenge,
+    #[msg("Challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("Challenge window is still open")]
+    ChallengeWindowOpen,
+    #[msg("Proposal has no open challenge")]
+    NotChallenged,
+    #[msg("Proposal must be finalized after its challenge window")]
+    FinalizationRequired,
+    #[msg("Active set needs more approvals than a proposal can hold")]
+    VoterCapacityExceeded,
+    #[msg("Node still holds an undistributed slash for another proposal")]
+    SlashPendingDistribution,
+    #[msg("Amount exceeds the forfeited challenge bonds")]
+    InsufficientForfeitedBonds,
+    #[msg("Challenge is still within its resolution period")]
+    ChallengeResolutionPending,
+    #[msg("Forfeited challenge bonds must be withdrawn first")]
+    ForfeitedBondsOutstanding,
+    #[msg("Too many open proposals; wait for one to be decided or expire")]
+    PendingRootsFull,
+}
+
+// Events
+#[event]
+pub struct NodeRegistered {
+    pub node: Pubkey,
+    pub stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ObserverRegistered {
+    pub observer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalCreated {
+    pub proposal_id: u64,
+    pub neural_state_root: [u8; 32],
+    pub proposer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ConsensusReached {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub neural_state_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalRejected {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExpired {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub neural_state_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeIncreased {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
 pub struct StakeDecreased {
     pub node: Pubkey,
     pub amount: u64,
@@ -349,35 +2895,1040 @@ pub struct StakeDecreased {
 }
 
 #[event]
-pub struct NodeDeregistered {
+pub struct NodeDeregistered {
+    pub node: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NodeClosed {
+    pub node: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NeuralStateUpdated {
+    pub node: Pubkey,
+    pub neural_state_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeDistribution {
+    pub node_count: u64,
+    pub total_stake: u64,
+    pub gini_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolTvl {
+    pub total_staked: u64,
+    pub total_active_stake: u64,
+    pub active_node_count: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DecentralizationScore {
+    pub node_count: u64,
+    pub gini_bps: u64,
+    pub distinct_regions: u64,
+    pub decentralization_score: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NodePauseSet {
+    pub node: Pubkey,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteDelegateSet {
+    pub node: Pubkey,
+    pub delegate: Option<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReputationUpdated {
+    pub node: Pubkey,
+    pub old_reputation: u64,
+    pub new_reputation: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NodeSlashed {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub reason: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EpochAdvanced {
+    pub epoch: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnstakeRequested {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub available_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnstakeClaimed {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NodeAutoDeactivated {
+    pub node: Pubkey,
+    pub missed_epochs: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferStarted {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolPauseSet {
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolParamsUpdated {
+    pub min_stake: u64,
+    pub epoch_duration: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NodeDeactivated {
+    pub node: Pubkey,
+    pub stake: u64,
+    pub min_stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardDistributed {
+    pub epoch: u64,
+    pub amount: u64,
+    pub total_active_stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardClaimed {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SlashRedistributed {
+    pub slashed_node: Pubkey,
+    pub proposal: Pubkey,
+    pub amount: u64,
+    pub recipients: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct NodeMetadataUpdated {
+    pub node: Pubkey,
+    pub metadata_uri: String,
+    pub gossip_pubkey: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalChallenged {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub challenger: Pubkey,
+    pub bond: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeResolved {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub challenger: Pubkey,
+    pub upheld: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeExpired {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub challenger: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalFinalized {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ForfeitedBondsWithdrawn {
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteDropped {
+    pub proposal_id: u64,
+    pub proposal: Pubkey,
     pub node: Pubkey,
     pub timestamp: i64,
 }
 
 // Helper functions
 impl ProtocolState {
-    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 1;
+    pub const LEN: usize = 32 // admin
+        + 8 // min_stake
+        + 8 // epoch_duration
+        + 8 // active_node_count
+        + 8 // proposal_count
+        + 8 // last_epoch_update
+        + 1 // vault_authority_bump
+        + 8 // sync_window
+        + 8 // execution_delay
+        + 8 // min_active_epochs
+        + 1 // min_distinct_regions
+        + 1 // computation_difficulty
+        + 8 // cleanup_delay
+        + 1 // require_independent_vote
+        + 8 // reputation_reward
+        + 8 // slash_bps
+        + 8 // slash_reputation_penalty
+        + 8 // epoch
+        + 8 // unstake_cooldown
+        + 8 // max_missed_epochs
+        + 8 // total_active_stake
+        + 1 // stake_weighted
+        + 8 // recent_proposer_epochs
+        + 8 // recent_proposer_penalty_bps
+        + 8 // proposal_ttl
+        + 4 // min_client_version
+        + 8 // rejection_threshold_bps
+        + (1 + 32) // pending_admin
+        + 1 // paused
+        + 8 // reputation_participation_floor
+        + 8 // consensus_threshold_bps
+        + 8 // reputation_decay_bps
+        + 8 // reputation_floor
+        + 32 // reward_pool
+        + 8 // epoch_reward
+        + 16 // reward_per_stake
+        + 8 // last_rewarded_epoch
+        + 8 // reward_liabilities
+        + 8 // min_reputation_vote_interval
+        + 8 // slash_redistribution_bps
+        + 8 // min_proposer_reputation
+        + 8 // min_proposer_stake
+        + 8 // proposal_cooldown
+        + Checkpoint::LEN * MAX_CHECKPOINTS // checkpoints
+        + 8 // checkpoint_count
+        + 32 // stake_mint
+        + 32 // protocol_vault
+        + PendingRoot::LEN * MAX_PENDING_ROOTS // pending_roots
+        + 8 // total_staked
+        + 8 // challenge_period
+        + 8 // challenge_bond
+        + 8 // forfeited_bonds
+        + 8; // challenge_resolution_period
+
+    /// The most recently executed root, if any proposal has executed
+    pub fn current_root(&self) -> Option<[u8; 32]> {
+        let latest = self.checkpoint_count.checked_sub(1)?;
+        Some(self.checkpoints[(latest % MAX_CHECKPOINTS as u64) as usize].neural_state_root)
+    }
+
+    /// Records an executed root, overwriting the oldest checkpoint when full
+    pub fn record_checkpoint(&mut self, neural_state_root: [u8; 32], timestamp: i64) -> Result<()> {
+        let slot = (self.checkpoint_count % MAX_CHECKPOINTS as u64) as usize;
+        self.checkpoints[slot] = Checkpoint {
+            neural_state_root,
+            epoch: self.epoch,
+            timestamp,
+        };
+        self.checkpoint_count = self
+            .checkpoint_count
+            .checked_add(1)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Registers an open proposal's root, failing if another open proposal
+    /// already carries it. Fails when every slot is open rather than evicting
+    /// one, which would let that root be proposed again.
+    pub fn claim_pending_root(&mut self, entry: PendingRoot) -> Result<()> {
+        let now = entry.created_at;
+        require!(
+            !self.pending_roots.iter().any(|pending| {
+                pending.is_open(now) && pending.neural_state_root == entry.neural_state_root
+            }),
+            SynapseError::DuplicateProposal
+        );
+
+        let slot = self
+            .pending_roots
+            .iter()
+            .position(|pending| !pending.is_open(now))
+            .ok_or(SynapseError::PendingRootsFull)?;
+        self.pending_roots[slot] = entry;
+        Ok(())
+    }
+
+    /// Frees a proposal's root once it is decided or expired
+    pub fn release_pending_root(&mut self, proposal: &Pubkey) {
+        for pending in self.pending_roots.iter_mut() {
+            if pending.proposal == *proposal {
+                *pending = PendingRoot::default();
+            }
+        }
+    }
+
+    /// Whether `now` falls in the sync window opening the current epoch,
+    /// during which nodes converge on their roots and proposals are deferred
+    pub fn in_sync_window(&self, now: i64) -> bool {
+        if self.sync_window == 0 || self.epoch_duration <= 0 {
+            return false;
+        }
+        let elapsed = now.saturating_sub(self.last_epoch_update);
+        elapsed >= 0 && elapsed % self.epoch_duration < self.sync_window
+    }
+
+    // total_active_stake covers active, unpaused nodes, mirroring
+    // active_node_count for stake-weighted consensus
+    pub fn add_active_stake(&mut self, amount: u64) -> Result<()> {
+        self.total_active_stake = self
+            .total_active_stake
+            .checked_add(amount)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn remove_active_stake(&mut self, amount: u64) -> Result<()> {
+        self.total_active_stake = self
+            .total_active_stake
+            .checked_sub(amount)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
+
+    // A proposal holds at most MAX_PROPOSAL_VOTERS approvals, so an active set
+    // needing more of them could never reach node-count consensus. Stake-weighted
+    // quorum doesn't count nodes; set_stake_weighted checks on switching back.
+    pub fn require_voter_capacity(&self) -> Result<()> {
+        if self.stake_weighted {
+            return Ok(());
+        }
+        require!(
+            self.active_node_count as u128 * self.consensus_threshold_bps as u128
+                <= MAX_PROPOSAL_VOTERS as u128 * BPS_DENOMINATOR as u128,
+            SynapseError::VoterCapacityExceeded
+        );
+        Ok(())
+    }
+
+    // total_staked is every node's stake, active or not, and only feeds TVL
+    // reporting; stake-weighted quorum keeps using total_active_stake
+    pub fn add_staked(&mut self, amount: u64) -> Result<()> {
+        self.total_staked = self
+            .total_staked
+            .checked_add(amount)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn remove_staked(&mut self, amount: u64) -> Result<()> {
+        self.total_staked = self
+            .total_staked
+            .checked_sub(amount)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
 }
 
 impl NodeState {
-    pub const LEN: usize = 32 + 8 + 8 + 8 + 32 + 1;
+    pub const LEN: usize = 32 // owner
+        + 8 // stake
+        + 8 // reputation
+        + 8 // last_update
+        + 32 // neural_state_root
+        + 1 // is_active
+        + (1 + 32) // vote_delegate
+        + 8 // registered_epoch
+        + 1 // region
+        + 1 // is_paused
+        + 8 // pending_unstake
+        + 8 // unstake_available_at
+        + 8 // last_heartbeat_epoch
+        + 8 // missed_epochs
+        + (1 + 8) // last_successful_proposal_epoch
+        + 4 // client_version
+        + 8 // epochs_inactive
+        + 16 // reward_debt
+        + 8 // accrued_rewards
+        + 8 // last_reputation_vote_at
+        + 8 // undistributed_slash
+        + 8 // last_proposal_at
+        + 4 + MAX_METADATA_URI_LEN // metadata_uri
+        + 32 // gossip_pubkey
+        + 32 // protocol_state
+        + 32 // slashed_proposal
+        + 8 // slash_distribution_base
+        + MAX_PROPOSAL_VOTERS / 8 // slash_settled
+        + 8; // last_update_epoch
+
+    // Resets a node to a freshly registered, active state with the given
+    // stake and adds it to the active set. Any pending unstake is kept.
+    pub fn activate(
+        &mut self,
+        protocol_state: &mut ProtocolState,
+        stake_amount: u64,
+        neural_state_root: [u8; 32],
+        region: u8,
+        client_version: u32,
+    ) -> Result<()> {
+        self.stake = stake_amount;
+        self.reputation = 1000; // Base reputation
+        self.record_update(protocol_state.epoch, Clock::get()?.unix_timestamp);
+        self.neural_state_root = neural_state_root;
+        self.is_active = true;
+        self.vote_delegate = None;
+        self.registered_epoch = protocol_state.epoch;
+        self.region = region;
+        self.is_paused = false;
+        self.last_heartbeat_epoch = protocol_state.epoch;
+        self.missed_epochs = 0;
+        self.last_successful_proposal_epoch = None;
+        self.client_version = client_version;
+        self.sync_reward_debt(protocol_state);
+
+        protocol_state.active_node_count = protocol_state
+            .active_node_count
+            .checked_add(1)
+            .ok_or(SynapseError::MathOverflow)?;
+        protocol_state.require_voter_capacity()?;
+        protocol_state.add_staked(stake_amount)?;
+        protocol_state.add_active_stake(stake_amount)
+    }
+
+    pub fn set_metadata(&mut self, metadata_uri: String, gossip_pubkey: [u8; 32]) -> Result<()> {
+        require!(
+            metadata_uri.len() <= MAX_METADATA_URI_LEN,
+            SynapseError::MetadataUriTooLong
+        );
+
+        self.metadata_uri = metadata_uri;
+        self.gossip_pubkey = gossip_pubkey;
+        Ok(())
+    }
+
+    // Marks the node as active in `epoch`, ending any idle streak
+    pub fn record_update(&mut self, epoch: u64, now: i64) {
+        self.last_update = now;
+        self.last_update_epoch = epoch;
+        self.epochs_inactive = 0;
+    }
+
+    // Whether an approval now earns reputation; starts a new interval if so
+    pub fn take_reputation_vote(&mut self, protocol_state: &ProtocolState, now: i64) -> bool {
+        let spaced = self.last_reputation_vote_at == 0
+            || now.saturating_sub(self.last_reputation_vote_at)
+                >= protocol_state.min_reputation_vote_interval;
+        if spaced {
+            self.last_reputation_vote_at = now;
+        }
+        spaced
+    }
+
+    // Stake earning rewards; only active, unpaused nodes accrue
+    pub fn reward_weight(&self) -> u64 {
+        if self.is_active && !self.is_paused {
+            self.stake
+        } else {
+            0
+        }
+    }
+
+    // Credits rewards accrued at the current weight since the last settlement.
+    // Call before the node's reward weight changes, and sync_reward_debt after.
+    pub fn settle_rewards(&mut self, protocol_state: &ProtocolState) -> Result<()> {
+        let accumulated = self.reward_weight() as u128 * protocol_state.reward_per_stake
+            / REWARD_PRECISION;
+        let pending = accumulated.saturating_sub(self.reward_debt);
+        self.accrued_rewards = self
+            .accrued_rewards
+            .checked_add(pending as u64)
+            .ok_or(SynapseError::MathOverflow)?;
+        self.reward_debt = accumulated;
+        Ok(())
+    }
+
+    pub fn sync_reward_debt(&mut self, protocol_state: &ProtocolState) {
+        self.reward_debt =
+            self.reward_weight() as u128 * protocol_state.reward_per_stake / REWARD_PRECISION;
+    }
+
+    // Stake-based voting weight. After a successful proposal the weight is cut
+    // by recent_proposer_penalty_bps, shrinking linearly to nothing over
+    // recent_proposer_epochs.
+    pub fn voting_weight(&self, protocol_state: &ProtocolState) -> u64 {
+        let elapsed = match self.last_successful_proposal_epoch {
+            Some(epoch) => protocol_state.epoch.saturating_sub(epoch),
+            None => return self.stake,
+        };
+        if elapsed >= protocol_state.recent_proposer_epochs {
+            return self.stake;
+        }
+
+        let penalty_bps = protocol_state.recent_proposer_penalty_bps as u128
+            * (protocol_state.recent_proposer_epochs - elapsed) as u128
+            / protocol_state.recent_proposer_epochs as u128;
+        (self.stake as u128 * (BPS_DENOMINATOR as u128 - penalty_bps) / BPS_DENOMINATOR as u128)
+            as u64
+    }
+}
+
+impl Proposal {
+    pub const LEN: usize = std::mem::size_of::<Proposal>();
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expired != 0 || now > self.expires_at
+    }
+
+    pub fn voters(&self) -> &[Pubkey] {
+        &self.voters[..self.vote_count as usize]
+    }
+
+    pub fn dissenters(&self) -> &[Pubkey] {
+        &self.dissenters[..self.no_vote_count as usize]
+    }
+
+    // Scans the filled slots in place, without copying the voter arrays
+    pub fn has_voted(&self, voter: &Pubkey) -> bool {
+        self.voters().contains(voter) || self.dissenters().contains(voter)
+    }
+
+    // Flags the most recently recorded approval as earning reputation
+    pub fn mark_reputation_eligible(&mut self) {
+        if let Some(index) = self.vote_count.checked_sub(1) {
+            set_bit(&mut self.reputation_eligible, index as usize);
+        }
+    }
+
+    pub fn record_ballot(
+        &mut self,
+        voter: Pubkey,
+        weight: u64,
+        region: u8,
+        support: bool,
+    ) -> Result<()> {
+        if support {
+            self.record_vote(voter, weight)?;
+            mark_region(&mut self.approval_regions, region);
+        } else {
+            self.record_dissent(voter, weight)?;
+        }
+        Ok(())
+    }
+
+    // Records an approving vote, failing cleanly once the voter list is full
+    pub fn record_vote(&mut self, voter: Pubkey, weight: u64) -> Result<()> {
+        require!(!self.has_voted(&voter), SynapseError::AlreadyVoted);
+        require!(
+            (self.vote_count as usize) < MAX_PROPOSAL_VOTERS,
+            SynapseError::ProposalFull
+        );
+
+        self.voters[self.vote_count as usize] = voter;
+        self.voter_weights[self.vote_count as usize] = weight;
+        self.vote_count = self
+            .vote_count
+            .checked_add(1)
+            .ok_or(SynapseError::MathOverflow)?;
+        self.total_stake_voted = self
+            .total_stake_voted
+            .checked_add(weight)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Records a vote against; a node gets one vote in either direction
+    pub fn record_dissent(&mut self, voter: Pubkey, weight: u64) -> Result<()> {
+        require!(!self.has_voted(&voter), SynapseError::AlreadyVoted);
+        require!(
+            (self.no_vote_count as usize) < MAX_PROPOSAL_VOTERS,
+            SynapseError::ProposalFull
+        );
+
+        self.dissenters[self.no_vote_count as usize] = voter;
+        self.dissenter_weights[self.no_vote_count as usize] = weight;
+        self.no_vote_count = self
+            .no_vote_count
+            .checked_add(1)
+            .ok_or(SynapseError::MathOverflow)?;
+        self.total_stake_against = self
+            .total_stake_against
+            .checked_add(weight)
+            .ok_or(SynapseError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+impl Observer {
+    pub const LEN: usize = 32 + 32 + 8;
+}
+
+// Rejects remaining_accounts that list a node more than once, which would
+// count its stake twice in the metrics computed over them
+fn require_distinct_accounts(accounts: &[AccountInfo]) -> Result<()> {
+    for (i, account_info) in accounts.iter().enumerate() {
+        require!(
+            accounts[..i].iter().all(|other| other.key != account_info.key),
+            SynapseError::InvalidParameter
+        );
+    }
+    Ok(())
+}
+
+// Gini coefficient in basis points: 0 when all stakes are equal, approaching
+// 10000 as stake concentrates in a single node (max (n - 1) / n for n nodes)
+fn gini_coefficient_bps(stakes: &mut [u64]) -> u64 {
+    let n = stakes.len() as u128;
+    let total: u128 = stakes.iter().map(|stake| *stake as u128).sum();
+    if n == 0 || total == 0 {
+        return 0;
+    }
+
+    stakes.sort_unstable();
+    let weighted: u128 = stakes
+        .iter()
+        .enumerate()
+        .map(|(i, stake)| (i as u128 + 1) * *stake as u128)
+        .sum();
+
+    ((2 * weighted).saturating_sub((n + 1) * total) * BPS_DENOMINATOR as u128
+        / (n * total)) as u64
 }
 
+// Verifies the signature via the Ed25519SigVerify native program: the
+// instruction immediately preceding this one must check `signature` by
+// `signer` over `neural_state_root`, with all three stored in its own data
 fn verify_signature(
     neural_state_root: &[u8; 32],
     signature: &[u8],
     signer: &Pubkey,
+    instructions: &AccountInfo,
 ) -> bool {
-    // Implement Ed25519 signature verification
-    // This is a placeholder - actual implementation would use ed25519_dalek
-    true
+    if signature.len() != ED25519_SIGNATURE_LEN {
+        return false;
+    }
+
+    let current_index = match load_current_index_checked(instructions) {
+        Ok(index) if index > 0 => index,
+        _ => return false,
+    };
+    let ix = match load_instruction_at_checked(current_index as usize - 1, instructions) {
+        Ok(ix) => ix,
+        Err(_) => return false,
+    };
+    if ix.program_id != ed25519_program::ID || !ix.accounts.is_empty() {
+        return false;
+    }
+
+    let data = &ix.data;
+    if data.len() < ED25519_OFFSETS_START + ED25519_OFFSETS_LEN || data[0] != 1 {
+        return false;
+    }
+    let read_u16 = |at: usize| {
+        let at = ED25519_OFFSETS_START + at;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let signature_offset = read_u16(0) as usize;
+    let signature_ix_index = read_u16(2);
+    let pubkey_offset = read_u16(4) as usize;
+    let pubkey_ix_index = read_u16(6);
+    let message_offset = read_u16(8) as usize;
+    let message_size = read_u16(10) as usize;
+    let message_ix_index = read_u16(12);
+
+    // u16::MAX points the native program at the Ed25519 instruction's own data
+    if signature_ix_index != u16::MAX
+        || pubkey_ix_index != u16::MAX
+        || message_ix_index != u16::MAX
+        || message_size != neural_state_root.len()
+    {
+        return false;
+    }
+
+    data.get(pubkey_offset..pubkey_offset + ED25519_PUBKEY_LEN) == Some(signer.as_ref())
+        && data.get(signature_offset..signature_offset + ED25519_SIGNATURE_LEN)
+            == Some(signature)
+        && data.get(message_offset..message_offset + message_size)
+            == Some(neural_state_root.as_ref())
+}
+
+// Multiplicative decay for each completed epoch without a last_update. The
+// idle streak is derived from last_update_epoch, like missed_epochs from
+// last_heartbeat_epoch, and epochs_inactive counts the idle epochs already
+// decayed, so a node left out of advance_epoch catches up the next time it is
+// passed. The rate grows with the streak, and decay never takes reputation
+// below reputation_floor.
+fn decay_reputation(protocol_state: &ProtocolState, node: &mut NodeState) {
+    let idle_epochs = protocol_state
+        .epoch
+        .saturating_sub(node.last_update_epoch)
+        .saturating_sub(1);
+
+    while node.epochs_inactive < idle_epochs {
+        if protocol_state.reputation_decay_bps == 0
+            || node.reputation <= protocol_state.reputation_floor
+        {
+            break;
+        }
+        node.epochs_inactive += 1;
+
+        let decay_bps = protocol_state
+            .reputation_decay_bps
+            .saturating_mul(node.epochs_inactive)
+            .min(BPS_DENOMINATOR);
+        let decayed = (node.reputation as u128 * (BPS_DENOMINATOR - decay_bps) as u128
+            / BPS_DENOMINATOR as u128) as u64;
+        node.reputation = decayed.max(protocol_state.reputation_floor);
+    }
+    node.epochs_inactive = idle_epochs;
+}
+
+// Deactivates a node left below a raised min_stake instead of letting it act.
+// Its stake isn't slashed, and deregister_node still lets the owner withdraw.
+fn deactivate_if_understaked(
+    protocol_state: &mut ProtocolState,
+    node_state: &mut NodeState,
+) -> Result<bool> {
+    if node_state.stake >= protocol_state.min_stake {
+        return Ok(false);
+    }
+
+    node_state.settle_rewards(protocol_state)?;
+    if !node_state.is_paused {
+        protocol_state.active_node_count = protocol_state
+            .active_node_count
+            .checked_sub(1)
+            .ok_or(SynapseError::MathOverflow)?;
+        protocol_state.remove_active_stake(node_state.stake)?;
+    }
+    node_state.is_active = false;
+    node_state.is_paused = false;
+    node_state.sync_reward_debt(protocol_state);
+
+    emit!(NodeDeactivated {
+        node: node_state.owner,
+        stake: node_state.stake,
+        min_stake: protocol_state.min_stake,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(true)
+}
+
+// Transfers `amount` from a staker into the protocol vault and returns what the
+// vault actually received, which falls short of `amount` for transfer-fee mints
+fn deposit_to_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = vault.amount;
+    let transfer_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: vault.to_account_info(),
+            authority: authority.to_account_info(),
+        },
+    );
+    token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)?;
+
+    vault.reload()?;
+    let received = vault
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(SynapseError::MathOverflow)?;
+    Ok(received)
+}
+
+// Deserializes a NodeState passed in remaining_accounts, rejecting nodes
+// registered under a different ProtocolState
+fn node_account<'info>(
+    account_info: &AccountInfo<'info>,
+    protocol_state: Pubkey,
+) -> Result<Account<'info, NodeState>> {
+    let node = Account::<NodeState>::try_from(account_info)?;
+    require_keys_eq!(
+        node.protocol_state,
+        protocol_state,
+        SynapseError::InvalidParameter
+    );
+    Ok(node)
+}
+
+// Moves `amount` of active stake into the pending unstake balance, claimable
+// once the unstake cooldown elapses. Further requests restart the cooldown.
+fn queue_unstake(
+    protocol_state: &mut ProtocolState,
+    node_state: &mut NodeState,
+    amount: u64,
+) -> Result<()> {
+    require!(
+        protocol_state.epoch
+            >= node_state
+                .registered_epoch
+                .checked_add(protocol_state.min_active_epochs)
+                .ok_or(SynapseError::MathOverflow)?,
+        SynapseError::MinActivePeriodNotMet
+    );
+    let remaining_stake = node_state
+        .stake
+        .checked_sub(amount)
+        .ok_or(SynapseError::MathOverflow)?;
+    require!(
+        remaining_stake >= protocol_state.min_stake,
+        SynapseError::InsufficientStake
+    );
+
+    node_state.settle_rewards(protocol_state)?;
+    node_state.stake = remaining_stake;
+    protocol_state.remove_staked(amount)?;
+    if !node_state.is_paused {
+        protocol_state.remove_active_stake(amount)?;
+    }
+    node_state.sync_reward_debt(protocol_state);
+    node_state.pending_unstake = node_state
+        .pending_unstake
+        .checked_add(amount)
+        .ok_or(SynapseError::MathOverflow)?;
+    node_state.unstake_available_at = Clock::get()?
+        .unix_timestamp
+        .checked_add(protocol_state.unstake_cooldown)
+        .ok_or(SynapseError::MathOverflow)?;
+    Ok(())
+}
+
+fn check_consensus(
+    proposal_key: Pubkey,
+    proposal: &mut Proposal,
+    protocol_state: &mut Account<ProtocolState>,
+    proposer_state: &mut NodeState,
+) -> Result<()> {
+    // Check for consensus threshold, by stake or by node count
+    let (approved, total) = if protocol_state.stake_weighted {
+        (proposal.total_stake_voted, proposal.snapshot_total_stake)
+    } else {
+        (proposal.vote_count, proposal.snapshot_node_count)
+    };
+    if proposal.consensus_reached != 0
+        || (approved as u128) * (BPS_DENOMINATOR as u128)
+            < (total as u128) * (protocol_state.consensus_threshold_bps as u128)
+    {
+        return Ok(());
+    }
+
+    // The proposer's own approval can't finalize a proposal on its own
+    if protocol_state.require_independent_vote
+        && proposal.voters().iter().all(|voter| *voter == proposal.proposer)
+    {
+        return Ok(());
+    }
+
+    // Optionally require approvals to span enough distinct regions
+    if count_regions(&proposal.approval_regions) < protocol_state.min_distinct_regions as u32 {
+        return Ok(());
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    proposal.consensus_reached = 1;
+    protocol_state.release_pending_root(&proposal_key);
+    // Share of the active set that voted either way, snapshotted at consensus
+    proposal.participation_bps = if proposal.snapshot_node_count == 0 {
+        0
+    } else {
+        ((proposal.vote_count + proposal.no_vote_count) as u128 * BPS_DENOMINATOR as u128
+            / proposal.snapshot_node_count as u128) as u64
+    };
+    proposal.executable_at = now
+        .checked_add(protocol_state.execution_delay)
+        .ok_or(SynapseError::MathOverflow)?;
+    if protocol_state.challenge_period > 0 {
+        proposal.challenge_ends_at = now
+            .checked_add(protocol_state.challenge_period)
+            .ok_or(SynapseError::MathOverflow)?;
+    }
+
+    emit!(ConsensusReached {
+        proposal_id: proposal.proposal_id,
+        proposal: proposal_key,
+        neural_state_root: proposal.neural_state_root,
+        timestamp: now,
+    });
+
+    // Without a timelock or challenge window the proposal takes effect immediately
+    if protocol_state.execution_delay == 0 && protocol_state.challenge_period == 0 {
+        execute_root(proposal_key, proposal, protocol_state, proposer_state, now)?;
+    }
+
+    Ok(())
 }
 
-fn update_reputations(
-    proposal: &Account<Proposal>,
-    protocol_state: &Account<ProtocolState>,
+// Applies an accepted root: marks the proposal executed, checkpoints it and
+// starts the proposer's recent-proposer penalty from the current epoch
+fn execute_root(
+    proposal_key: Pubkey,
+    proposal: &mut Proposal,
+    protocol_state: &mut Account<ProtocolState>,
+    proposer_state: &mut NodeState,
+    now: i64,
 ) -> Result<()> {
-    // Implement reputation updates based on voting alignment
-    // This would update each node's reputation score based on their votes
+    proposal.executed = 1;
+    protocol_state.record_checkpoint(proposal.neural_state_root, now)?;
+    proposer_state.last_successful_proposal_epoch = Some(protocol_state.epoch);
+
+    emit!(ProposalExecuted {
+        proposal_id: proposal.proposal_id,
+        proposal: proposal_key,
+        neural_state_root: proposal.neural_state_root,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+// Marks the proposal failed once votes against reach rejection_threshold_bps of
+// the active set snapshotted at creation, by stake or by node count
+fn check_rejection(
+    proposal_key: Pubkey,
+    proposal: &mut Proposal,
+    protocol_state: &mut Account<ProtocolState>,
+) -> Result<()> {
+    if proposal.rejected != 0 || proposal.consensus_reached != 0 {
+        return Ok(());
+    }
+
+    let (against, total) = if protocol_state.stake_weighted {
+        (proposal.total_stake_against, proposal.snapshot_total_stake)
+    } else {
+        (proposal.no_vote_count, proposal.snapshot_node_count)
+    };
+    if (against as u128) * (BPS_DENOMINATOR as u128)
+        < (total as u128) * (protocol_state.rejection_threshold_bps as u128)
+    {
+        return Ok(());
+    }
+
+    proposal.rejected = 1;
+    protocol_state.release_pending_root(&proposal_key);
+
+    emit!(ProposalRejected {
+        proposal_id: proposal.proposal_id,
+        proposal: proposal_key,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
     Ok(())
-} 
\ No newline at end of file
+}
+
+fn bit_is_set(bits: &[u8], index: usize) -> bool {
+    bits[index / 8] & (1 << (index % 8)) != 0
+}
+
+fn set_bit(bits: &mut [u8], index: usize) {
+    bits[index / 8] |= 1 << (index % 8);
+}
+
+fn mark_region(regions: &mut [u8; 32], region: u8) {
+    regions[(region / 8) as usize] |= 1 << (region % 8);
+}
+
+fn count_regions(regions: &[u8; 32]) -> u32 {
+    regions.iter().map(|byte| byte.count_ones()).sum()
+}
+
+// Proof-of-computation: sha256(root || proposer || nonce_le) must start with
+// at least `difficulty` zero bits. A difficulty of zero disables the check.
+fn verify_computation_proof(
+    neural_state_root: &[u8; 32],
+    proposer: &Pubkey,
+    nonce: u64,
+    difficulty: u8,
+) -> bool {
+    if difficulty == 0 {
+        return true;
+    }
+
+    let digest = hashv(&[
+        neural_state_root.as_ref(),
+        proposer.as_ref(),
+        nonce.to_le_bytes().as_ref(),
+    ]);
+    let mut zero_bits = 0u32;
+    for byte in digest.to_bytes().iter() {
+        zero_bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zero_bits >= difficulty as u32
+}
+
+// Recomputes a Merkle root with sha256 throughout. Leaves are hashed as
+// sha256(0x00 || leaf) and inner nodes as sha256(0x01 || left || right), so a
+// leaf can't pass for an inner node. Bit i of `index` (from the least
+// significant) says whether the running hash is the right child at level i;
+// proof[i] is its sibling at that level.
+pub fn compute_merkle_root(leaf: &[u8; 32], index: u32, proof: &[[u8; 32]]) -> [u8; 32] {
+    let mut hash = hashv(&[&[0u8], leaf.as_ref()]).to_bytes();
+    for (level, sibling) in proof.iter().enumerate() {
+        hash = if (index >> level) & 1 == 0 {
+            hashv(&[&[1u8], hash.as_ref(), sibling.as_ref()]).to_bytes()
+        } else {
+            hashv(&[&[1u8], sibling.as_ref(), hash.as_ref()]).to_bytes()
+        };
+    }
+    hash
+}
\ No newline at end of file
@@ -3,11 +3,22 @@ import { Program } from "@project-serum/anchor";
 import { SynapseProtocol } from "../target/types/synapse_protocol";
 import {
   TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializePermanentDelegateInstruction,
   createMint,
   createAccount,
   mintTo,
+  getAccount,
+  transfer,
+  transferChecked,
 } from "@solana/spl-token";
 import { assert } from "chai";
+import { createHash } from "crypto";
+import nacl from "tweetnacl";
 
 describe("Nexar AI™ Synapse Protocol", () => {
   const provider = anchor.AnchorProvider.env();
@@ -25,6 +36,201 @@ describe("Nexar AI™ Synapse Protocol", () => {
   // Test parameters
   const MIN_STAKE = new anchor.BN(1000000); // 1 token
   const EPOCH_DURATION = new anchor.BN(300); // 5 minutes
+  const CLIENT_VERSION = 1;
+
+  // Node accounts are scoped to the protocol instance they registered with
+  const findNodeState = async (
+    owner: anchor.web3.PublicKey,
+    state = protocolState.publicKey
+  ) =>
+    (
+      await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("node"), state.toBuffer(), owner.toBuffer()],
+        program.programId
+      )
+    )[0];
+
+  // Node account of a proposal's proposer, which execution credits
+  const proposerStateOf = async (
+    proposal: anchor.web3.PublicKey,
+    state = protocolState.publicKey
+  ) => findNodeState((await program.account.proposal.fetch(proposal)).proposer, state);
+
+  // Ed25519SigVerify instruction proving the node signed the root
+  const signRoot = (node: anchor.web3.Keypair, neuralStateRoot: Buffer) => {
+    const signature = Buffer.from(nacl.sign.detached(neuralStateRoot, node.secretKey));
+    const instruction = anchor.web3.Ed25519Program.createInstructionWithPublicKey({
+      publicKey: node.publicKey.toBytes(),
+      message: neuralStateRoot,
+      signature,
+    });
+    return { signature, instruction };
+  };
+
+  // Stake vault of each protocol instance, owned by its vault authority PDA
+  const vaults = new Map<string, anchor.web3.PublicKey>();
+  const vaultFor = async (state: anchor.web3.PublicKey) => {
+    if (!vaults.has(state.toString())) {
+      const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+        [state.toBuffer()],
+        program.programId
+      );
+      const vault = await createAccount(
+        provider.connection,
+        provider.wallet.payer,
+        synapseToken,
+        vaultAuthority,
+        anchor.web3.Keypair.generate()
+      );
+      await program.methods
+        .setProtocolVault()
+        .accounts({
+          protocolState: state,
+          admin: provider.wallet.publicKey,
+          protocolVault: vault,
+          vaultAuthority,
+        })
+        .rpc();
+      vaults.set(state.toString(), vault);
+    }
+    return vaults.get(state.toString());
+  };
+
+  // Funds, stakes and registers a new node keypair
+  const registerFreshNode = async (
+    stake = new anchor.BN(1500000),
+    region = 0,
+    state = protocolState.publicKey,
+    clientVersion = CLIENT_VERSION
+  ) => {
+    const node = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        node.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      )
+    );
+    const tokenAccount = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      node.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      tokenAccount,
+      provider.wallet.payer,
+      BigInt(stake.toString())
+    );
+
+    const nodeState = await findNodeState(node.publicKey, state);
+    await program.methods
+      .registerNode(stake, Buffer.alloc(32, 1), region, clientVersion, "", Buffer.alloc(32))
+      .accounts({
+        protocolState: state,
+        nodeState,
+        staker: node.publicKey,
+        stakerTokenAccount: tokenAccount,
+        protocolVault: await vaultFor(state),
+        stakeMint: synapseToken,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([node])
+      .rpc();
+
+    return { node, tokenAccount, nodeState };
+  };
+
+  const castVote = async (
+    voter: anchor.web3.Keypair,
+    proposal: anchor.web3.PublicKey,
+    state = protocolState.publicKey,
+    support = true
+  ) =>
+    program.methods
+      .voteOnProposal(support)
+      .accounts({
+        protocolState: state,
+        nodeState: await findNodeState(voter.publicKey, state),
+        proposal,
+        proposerState: await proposerStateOf(proposal, state),
+        voter: voter.publicKey,
+      })
+      .signers([voter])
+      .rpc();
+
+  const setUnstakeCooldown = (seconds: number, state = protocolState.publicKey) =>
+    program.methods
+      .setUnstakeCooldown(new anchor.BN(seconds))
+      .accounts({
+        protocolState: state,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+  const claimUnstake = async (
+    node: anchor.web3.Keypair,
+    tokenAccount: anchor.web3.PublicKey,
+    state = protocolState.publicKey
+  ) => {
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [state.toBuffer()],
+      program.programId
+    );
+    return program.methods
+      .claimUnstake()
+      .accounts({
+        protocolState: state,
+        nodeState: await findNodeState(node.publicKey, state),
+        staker: node.publicKey,
+        stakerTokenAccount: tokenAccount,
+        protocolVault: await vaultFor(state),
+        vaultAuthority,
+        stakeMint: synapseToken,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([node])
+      .rpc();
+  };
+
+  // Proposals exceed what an instruction may allocate, so the client
+  // creates the account in the same transaction as the proposal
+  const allocateProposal = async (proposal: anchor.web3.Keypair) =>
+    anchor.web3.SystemProgram.createAccount({
+      fromPubkey: provider.wallet.publicKey,
+      newAccountPubkey: proposal.publicKey,
+      space: program.account.proposal.size,
+      lamports: await provider.connection.getMinimumBalanceForRentExemption(
+        program.account.proposal.size
+      ),
+      programId: program.programId,
+    });
+
+  const proposeRoot = async (
+    node: anchor.web3.Keypair,
+    proposal: anchor.web3.Keypair,
+    neuralStateRoot: Buffer,
+    nonce = new anchor.BN(0),
+    state = protocolState.publicKey
+  ) => {
+    const { signature, instruction } = signRoot(node, neuralStateRoot);
+    return program.methods
+      .proposeNeuralState(neuralStateRoot, signature, nonce)
+      .accounts({
+        protocolState: state,
+        nodeState: await findNodeState(node.publicKey, state),
+        proposal: proposal.publicKey,
+        staker: node.publicKey,
+        instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([await allocateProposal(proposal), instruction])
+      .signers([node, proposal])
+      .rpc();
+  };
   
   before(async () => {
     // Create protocol state account
@@ -39,13 +245,6 @@ describe("Nexar AI™ Synapse Protocol", () => {
       9 // 9 decimals
     );
     
-    // Create protocol vault
-    protocolVault = await createAccount(
-      provider.connection,
-      provider.wallet.payer,
-      synapseToken,
-      provider.wallet.publicKey
-    );
     
     // Create test node accounts
     for (let i = 0; i < 3; i++) {
@@ -83,9 +282,12 @@ describe("Nexar AI™ Synapse Protocol", () => {
       })
       .signers([protocolState])
       .rpc();
+    protocolVault = await vaultFor(protocolState.publicKey);
 
     const state = await program.account.protocolState.fetch(protocolState.publicKey);
     assert.equal(state.admin.toString(), provider.wallet.publicKey.toString());
+    assert.equal(state.protocolVault.toString(), protocolVault.toString());
+    assert.equal(state.stakeMint.toString(), synapseToken.toString());
     assert.equal(state.minStake.toString(), MIN_STAKE.toString());
     assert.equal(state.epochDuration.toString(), EPOCH_DURATION.toString());
     assert.equal(state.activeNodeCount.toString(), "0");
@@ -98,18 +300,19 @@ describe("Nexar AI™ Synapse Protocol", () => {
     const neuralStateRoot = Buffer.alloc(32, 1); // Test neural state
 
     const [nodeState] = await anchor.web3.PublicKey.findProgramAddress(
-      [Buffer.from("node"), node.publicKey.toBuffer()],
+      [Buffer.from("node"), protocolState.publicKey.toBuffer(), node.publicKey.toBuffer()],
       program.programId
     );
 
     await program.methods
-      .registerNode(stakeAmount, neuralStateRoot)
+      .registerNode(stakeAmount, neuralStateRoot, 0, CLIENT_VERSION, "", Buffer.alloc(32))
       .accounts({
         protocolState: protocolState.publicKey,
         nodeState,
         staker: node.publicKey,
         stakerTokenAccount: nodeTokenAccount,
         protocolVault,
+        stakeMint: synapseToken,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -126,30 +329,33 @@ describe("Nexar AI™ Synapse Protocol", () => {
   it("Proposes neural state update", async () => {
     const node = nodeStates[0];
     const neuralStateRoot = Buffer.alloc(32, 2); // New neural state
-    const signature = Buffer.alloc(64); // Mock signature
+    const { signature, instruction } = signRoot(node, neuralStateRoot);
 
     const [nodeState] = await anchor.web3.PublicKey.findProgramAddress(
-      [Buffer.from("node"), node.publicKey.toBuffer()],
+      [Buffer.from("node"), protocolState.publicKey.toBuffer(), node.publicKey.toBuffer()],
       program.programId
     );
 
     const proposal = anchor.web3.Keypair.generate();
 
     await program.methods
-      .proposeNeuralState(neuralStateRoot, signature)
+      .proposeNeuralState(neuralStateRoot, signature, new anchor.BN(0))
       .accounts({
         protocolState: protocolState.publicKey,
         nodeState,
         proposal: proposal.publicKey,
         staker: node.publicKey,
+        instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
       })
+      .preInstructions([await allocateProposal(proposal), instruction])
       .signers([node, proposal])
       .rpc();
 
     const proposalState = await program.account.proposal.fetch(proposal.publicKey);
     assert.equal(proposalState.proposer.toString(), node.publicKey.toString());
     assert.equal(proposalState.voteCount.toString(), "1");
-    assert.equal(proposalState.executed, false);
+    assert.equal(proposalState.executed, 0);
   });
 
   it("Votes on proposal", async () => {
@@ -157,16 +363,17 @@ describe("Nexar AI™ Synapse Protocol", () => {
     const proposal = anchor.web3.Keypair.generate();
 
     const [voterState] = await anchor.web3.PublicKey.findProgramAddress(
-      [Buffer.from("node"), voter.publicKey.toBuffer()],
+      [Buffer.from("node"), protocolState.publicKey.toBuffer(), voter.publicKey.toBuffer()],
       program.programId
     );
 
     await program.methods
-      .voteOnProposal()
+      .voteOnProposal(true)
       .accounts({
         protocolState: protocolState.publicKey,
         nodeState: voterState,
         proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey),
         voter: voter.publicKey,
       })
       .signers([voter])
@@ -182,7 +389,7 @@ describe("Nexar AI™ Synapse Protocol", () => {
     const increaseAmount = new anchor.BN(500000); // 0.5 tokens
 
     const [nodeState] = await anchor.web3.PublicKey.findProgramAddress(
-      [Buffer.from("node"), node.publicKey.toBuffer()],
+      [Buffer.from("node"), protocolState.publicKey.toBuffer(), node.publicKey.toBuffer()],
       program.programId
     );
 
@@ -194,6 +401,7 @@ describe("Nexar AI™ Synapse Protocol", () => {
         staker: node.publicKey,
         stakerTokenAccount: nodeTokenAccount,
         protocolVault,
+        stakeMint: synapseToken,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([node])
@@ -208,19 +416,19 @@ describe("Nexar AI™ Synapse Protocol", () => {
     const nodeTokenAccount = userTokenAccounts[0];
 
     const [nodeState] = await anchor.web3.PublicKey.findProgramAddress(
-      [Buffer.from("node"), node.publicKey.toBuffer()],
+      [Buffer.from("node"), protocolState.publicKey.toBuffer(), node.publicKey.toBuffer()],
       program.programId
     );
 
+    // Let the stake be claimed right away; cooldowns are covered separately
+    await setUnstakeCooldown(0);
+
     await program.methods
       .deregisterNode()
       .accounts({
         protocolState: protocolState.publicKey,
         nodeState,
         staker: node.publicKey,
-        stakerTokenAccount: nodeTokenAccount,
-        protocolVault,
-        tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([node])
       .rpc();
@@ -228,5 +436,3523 @@ describe("Nexar AI™ Synapse Protocol", () => {
     const state = await program.account.nodeState.fetch(nodeState);
     assert.equal(state.isActive, false);
     assert.equal(state.stake.toString(), "0");
+    assert.equal(state.pendingUnstake.toString(), "2000000");
+
+    await claimUnstake(node, nodeTokenAccount);
+    const claimed = await program.account.nodeState.fetch(nodeState);
+    assert.equal(claimed.pendingUnstake.toString(), "0");
+  });
+
+  it("Delegates voting power to another node", async () => {
+    const delegatorState = await findNodeState(nodeStates[1].publicKey);
+    const delegateState = await findNodeState(nodeStates[2].publicKey);
+
+    for (const [i, nodeState] of [[1, delegatorState], [2, delegateState]] as const) {
+      await program.methods
+        .registerNode(
+          new anchor.BN(1500000),
+          Buffer.alloc(32, 1),
+          0,
+          CLIENT_VERSION,
+          "",
+          Buffer.alloc(32)
+        )
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState,
+          staker: nodeStates[i].publicKey,
+          stakerTokenAccount: userTokenAccounts[i],
+          protocolVault,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([nodeStates[i]])
+        .rpc();
+    }
+
+    await program.methods
+      .setVoteDelegate(nodeStates[2].publicKey)
+      .accounts({ nodeState: delegatorState, owner: nodeStates[1].publicKey })
+      .remainingAccounts([
+        { pubkey: delegateState, isWritable: false, isSigner: false },
+      ])
+      .signers([nodeStates[1]])
+      .rpc();
+
+    const state = await program.account.nodeState.fetch(delegatorState);
+    assert.equal(state.voteDelegate.toString(), nodeStates[2].publicKey.toString());
+  });
+
+  it("Rejects delegation cycles", async () => {
+    const delegatorState = await findNodeState(nodeStates[1].publicKey);
+    const delegateState = await findNodeState(nodeStates[2].publicKey);
+
+    try {
+      await program.methods
+        .setVoteDelegate(nodeStates[1].publicKey)
+        .accounts({ nodeState: delegateState, owner: nodeStates[2].publicKey })
+        .remainingAccounts([
+          { pubkey: delegatorState, isWritable: false, isSigner: false },
+        ])
+        .signers([nodeStates[2]])
+        .rpc();
+      assert.fail("cyclic delegation should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "DelegationCycle");
+    }
+
+    // Delegating nodes can't vote directly; later tests vote with node 1
+    await program.methods
+      .setVoteDelegate(null)
+      .accounts({ nodeState: delegatorState, owner: nodeStates[1].publicKey })
+      .signers([nodeStates[1]])
+      .rpc();
+  });
+
+  it("Defers proposals during the epoch sync window", async () => {
+    const windowState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: windowState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([windowState])
+      .rpc();
+    const { node } = await registerFreshNode(undefined, 0, windowState.publicKey);
+    const propose = (proposal: anchor.web3.Keypair) =>
+      proposeRoot(node, proposal, Buffer.alloc(32, 3), undefined, windowState.publicKey);
+
+    // Close the window two seconds from now, measured on the cluster clock
+    const { lastEpochUpdate } = await program.account.protocolState.fetch(
+      windowState.publicKey
+    );
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+    const syncWindow = new anchor.BN(now).sub(lastEpochUpdate).addn(2);
+    await program.methods
+      .setSyncWindow(syncWindow)
+      .accounts({
+        protocolState: windowState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    try {
+      await propose(anchor.web3.Keypair.generate());
+      assert.fail("proposal inside the sync window should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InSyncWindow");
+    }
+
+    // The same window stops deferring proposals once that part of the epoch passes
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    const proposal = anchor.web3.Keypair.generate();
+    await propose(proposal);
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.proposer.toString(), node.publicKey.toString());
+    const state = await program.account.protocolState.fetch(windowState.publicKey);
+    assert.equal(state.syncWindow.toString(), syncWindow.toString());
+  });
+
+  it("Emits the Gini coefficient of stake distribution", async () => {
+    const nodeAccounts = await Promise.all(
+      [nodeStates[1], nodeStates[2]].map(async (node) => ({
+        pubkey: await findNodeState(node.publicKey),
+        isWritable: false,
+        isSigner: false,
+      }))
+    );
+
+    const { events } = await program.methods
+      .emitStakeDistribution()
+      .accounts({ protocolState: protocolState.publicKey })
+      .remainingAccounts(nodeAccounts)
+      .simulate();
+
+    const distribution = events.find((e) => e.name === "StakeDistribution");
+    assert.equal(distribution.data.nodeCount.toString(), "2");
+    // Both nodes staked 1.5 tokens, so the distribution is perfectly equal
+    assert.equal(distribution.data.giniBps.toString(), "0");
+
+    // Listing a node twice would weigh its stake double
+    try {
+      await program.methods
+        .emitStakeDistribution()
+        .accounts({ protocolState: protocolState.publicKey })
+        .remainingAccounts([nodeAccounts[0], nodeAccounts[0]])
+        .rpc();
+      assert.fail("duplicate node accounts should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidParameter");
+    }
+  });
+
+  it("Approaches (n - 1) / n Gini when one node holds most of the stake", async () => {
+    const giniState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: giniState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([giniState])
+      .rpc();
+
+    // One 1000-token node alongside four at the minimum stake
+    const nodes = [await registerFreshNode(new anchor.BN(1000000000), 0, giniState.publicKey)];
+    for (let i = 0; i < 4; i++) {
+      nodes.push(await registerFreshNode(MIN_STAKE, 0, giniState.publicKey));
+    }
+
+    const { events } = await program.methods
+      .emitStakeDistribution()
+      .accounts({ protocolState: giniState.publicKey })
+      .remainingAccounts(
+        nodes.map(({ nodeState }) => ({ pubkey: nodeState, isWritable: false, isSigner: false }))
+      )
+      .simulate();
+
+    const distribution = events.find((e) => e.name === "StakeDistribution");
+    assert.equal(distribution.data.nodeCount.toString(), "5");
+    // 4 * (1000 - 1) / (5 * (1000 + 4)) of the (n - 1) / n = 8000 bps limit
+    assert.equal(distribution.data.giniBps.toString(), "7960");
+    assert.isBelow(8000 - distribution.data.giniBps.toNumber(), 50);
+  });
+
+  it("Holds executed proposals behind the timelock", async () => {
+    const proposer = nodeStates[2];
+    const voter = nodeStates[1];
+    const proposal = anchor.web3.Keypair.generate();
+    const setDelay = (delay: number) =>
+      program.methods
+        .setExecutionDelay(new anchor.BN(delay))
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    const execute = () =>
+      program.methods
+        .executeProposal()
+        .accounts({
+          protocolState: protocolState.publicKey,
+          proposal: proposal.publicKey,
+          proposerState: await proposerStateOf(proposal.publicKey),
+          executor: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    await setDelay(2);
+
+    await proposeRoot(proposer, proposal, Buffer.alloc(32, 4));
+
+    await program.methods
+      .voteOnProposal(true)
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState: await findNodeState(voter.publicKey),
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey),
+        voter: voter.publicKey,
+      })
+      .signers([voter])
+      .rpc();
+
+    let proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.consensusReached, 1);
+    assert.equal(proposalState.executed, 0);
+
+    try {
+      await execute();
+      assert.fail("execution before the delay should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "TimelockActive");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await execute();
+
+    proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.executed, 1);
+
+    await setDelay(0);
+  });
+
+  it("Blocks unstaking before the minimum active period", async () => {
+    const node = nodeStates[1];
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [protocolState.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .setMinActiveEpochs(new anchor.BN(1))
+      .accounts({
+        protocolState: protocolState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .updateStake(new anchor.BN(100000), false)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState: await findNodeState(node.publicKey),
+          staker: node.publicKey,
+          stakerTokenAccount: userTokenAccounts[1],
+          protocolVault,
+          vaultAuthority,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([node])
+        .rpc();
+      assert.fail("unstaking inside the minimum active period should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "MinActivePeriodNotMet");
+    }
+
+    await program.methods
+      .setMinActiveEpochs(new anchor.BN(0))
+      .accounts({
+        protocolState: protocolState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+  });
+
+  it("Reevaluates an open proposal against current parameters", async () => {
+    const proposer = nodeStates[2];
+    const proposal = anchor.web3.Keypair.generate();
+
+    await proposeRoot(proposer, proposal, Buffer.alloc(32, 5));
+
+    await program.methods
+      .reevaluateProposal()
+      .accounts({
+        protocolState: protocolState.publicKey,
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey),
+      })
+      .rpc();
+
+    // A lone proposer vote out of two active nodes is still short of 67%
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.consensusReached, 0);
+    assert.equal(proposalState.executed, 0);
+  });
+
+  it("Requires approvals from distinct regions when configured", async () => {
+    const proposer = nodeStates[2];
+    const voter = nodeStates[1];
+    const proposal = anchor.web3.Keypair.generate();
+    const setMinRegions = (minRegions: number) =>
+      program.methods
+        .setMinDistinctRegions(minRegions)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    await setMinRegions(2);
+
+    await proposeRoot(proposer, proposal, Buffer.alloc(32, 6));
+
+    await program.methods
+      .voteOnProposal(true)
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState: await findNodeState(voter.publicKey),
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey),
+        voter: voter.publicKey,
+      })
+      .signers([voter])
+      .rpc();
+
+    // Every active node registered in region 0, so the majority can't finalize
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.consensusReached, 0);
+
+    await setMinRegions(0);
+  });
+
+  it("Keeps vault funds out of reach of everyone but the program", async () => {
+    const node = nodeStates[1];
+    const drain = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      provider.wallet.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+
+    await program.methods
+      .updateStake(new anchor.BN(100000), false)
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState: await findNodeState(node.publicKey),
+        staker: node.publicKey,
+        stakerTokenAccount: userTokenAccounts[1],
+        protocolVault,
+        stakeMint: synapseToken,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([node])
+      .rpc();
+
+    // The vault authority is a PDA, so not even the admin can move stake out
+    let drained = true;
+    try {
+      await transfer(
+        provider.connection,
+        provider.wallet.payer,
+        protocolVault,
+        drain,
+        provider.wallet.payer,
+        1
+      );
+    } catch (err) {
+      drained = false;
+    }
+    assert.isFalse(drained, "only the vault authority should move vault funds");
+
+    await claimUnstake(node, userTokenAccounts[1]);
+  });
+
+  it("Verifies proof-of-computation nonces", async () => {
+    const proposer = nodeStates[2];
+    const neuralStateRoot = Buffer.alloc(32, 7);
+    const setDifficulty = (difficulty: number) =>
+      program.methods
+        .setComputationDifficulty(difficulty)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    const propose = (nonce: anchor.BN) => {
+      const proposal = anchor.web3.Keypair.generate();
+      return proposeRoot(proposer, proposal, neuralStateRoot, nonce);
+    };
+
+    // Grind a nonce whose digest starts with a zero byte
+    let nonce = new anchor.BN(0);
+    const digest = (n: anchor.BN) =>
+      createHash("sha256")
+        .update(neuralStateRoot)
+        .update(proposer.publicKey.toBuffer())
+        .update(n.toArrayLike(Buffer, "le", 8))
+        .digest();
+    while (digest(nonce)[0] !== 0) {
+      nonce = nonce.addn(1);
+    }
+    const badNonce = digest(nonce.addn(1))[0] === 0 ? nonce.addn(2) : nonce.addn(1);
+
+    await setDifficulty(8);
+
+    try {
+      await propose(badNonce);
+      assert.fail("nonce below the difficulty should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidComputationProof");
+    }
+    await propose(nonce);
+
+    await setDifficulty(0);
+  });
+
+  it("Cleans up only long-inactive, unstaked nodes", async () => {
+    const inactiveNode = await findNodeState(nodeStates[0].publicKey);
+    const activeNode = await findNodeState(nodeStates[1].publicKey);
+    const cleanup = (nodeState: anchor.web3.PublicKey) =>
+      program.methods
+        .cleanupInactiveNodes()
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .remainingAccounts([{ pubkey: nodeState, isWritable: true, isSigner: false }])
+        .rpc();
+
+    await program.methods
+      .setCleanupDelay(new anchor.BN(0))
+      .accounts({
+        protocolState: protocolState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    try {
+      await cleanup(activeNode);
+      assert.fail("active nodes should not be cleaned up");
+    } catch (err) {
+      assert.include(err.toString(), "NodeNotEligibleForCleanup");
+    }
+
+    await cleanup(inactiveNode);
+    assert.isNull(await provider.connection.getAccountInfo(inactiveNode));
+    assert.isNotNull(await provider.connection.getAccountInfo(activeNode));
+  });
+
+  it("Commits a root and proposes it in one transaction", async () => {
+    const node = nodeStates[2];
+    const nodeState = await findNodeState(node.publicKey);
+    const proposal = anchor.web3.Keypair.generate();
+    const neuralStateRoot = Buffer.alloc(32, 8);
+    const { signature, instruction } = signRoot(node, neuralStateRoot);
+
+    await program.methods
+      .commitAndPropose(neuralStateRoot, signature, new anchor.BN(0))
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState,
+        proposal: proposal.publicKey,
+        staker: node.publicKey,
+        instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([await allocateProposal(proposal), instruction])
+      .signers([node, proposal])
+      .rpc();
+
+    const state = await program.account.nodeState.fetch(nodeState);
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.deepEqual(Buffer.from(state.neuralStateRoot), neuralStateRoot);
+    assert.deepEqual(Buffer.from(proposalState.neuralStateRoot), neuralStateRoot);
+  });
+
+  it("Pauses and resumes a node without touching its stake", async () => {
+    const node = nodeStates[1];
+    const nodeState = await findNodeState(node.publicKey);
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .setNodePaused(paused)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState,
+          owner: node.publicKey,
+        })
+        .signers([node])
+        .rpc();
+
+    const before = await program.account.protocolState.fetch(protocolState.publicKey);
+
+    await setPaused(true);
+    let state = await program.account.protocolState.fetch(protocolState.publicKey);
+    assert.equal(
+      state.activeNodeCount.toString(),
+      before.activeNodeCount.subn(1).toString()
+    );
+    const paused = await program.account.nodeState.fetch(nodeState);
+    assert.equal(paused.isPaused, true);
+    assert.equal(paused.stake.toString(), "1500000");
+
+    await setPaused(false);
+    state = await program.account.protocolState.fetch(protocolState.publicKey);
+    assert.equal(state.activeNodeCount.toString(), before.activeNodeCount.toString());
+  });
+
+  it("Rejects a signature over a different root", async () => {
+    const node = nodeStates[2];
+    const proposal = anchor.web3.Keypair.generate();
+    const { signature, instruction } = signRoot(node, Buffer.alloc(32, 9));
+
+    try {
+      await program.methods
+        .proposeNeuralState(Buffer.alloc(32, 10), signature, new anchor.BN(0))
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState: await findNodeState(node.publicKey),
+          proposal: proposal.publicKey,
+          staker: node.publicKey,
+          instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([await allocateProposal(proposal), instruction])
+        .signers([node, proposal])
+        .rpc();
+      assert.fail("a tampered root should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidSignature");
+    }
+  });
+
+  it("Caps the number of voters on a proposal", async () => {
+    const MAX_PROPOSAL_VOTERS = 256;
+    const proposer = nodeStates[2];
+    const proposal = anchor.web3.Keypair.generate();
+    const setMinRegions = (minRegions: number) =>
+      program.methods
+        .setMinDistinctRegions(minRegions)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    // Keep the proposal open while it fills by requiring unreachable diversity
+    await setMinRegions(255);
+    await proposeRoot(proposer, proposal, Buffer.alloc(32, 11));
+
+    const fillers = [];
+    for (let i = 0; i < MAX_PROPOSAL_VOTERS; i++) {
+      fillers.push(await registerFreshNode());
+    }
+    const computeUnits = async (signature: string) => {
+      await provider.connection.confirmTransaction(signature, "confirmed");
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return tx.meta.computeUnitsConsumed;
+    };
+    const units = [];
+    for (const { node } of fillers.slice(0, MAX_PROPOSAL_VOTERS - 1)) {
+      units.push(await computeUnits(await castVote(node, proposal.publicKey)));
+    }
+
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.voteCount.toString(), MAX_PROPOSAL_VOTERS.toString());
+    // Filling the last slot has to stay well inside the default 200k CU
+    // budget, and cost only the duplicate-vote scan more than the first vote.
+    // There is no pre-zero-copy baseline to compare against: the Borsh-encoded
+    // proposal ran out of heap long before reaching this many voters.
+    assert.isBelow(units[units.length - 1], 100000);
+    assert.isBelow(units[units.length - 1] - units[0], 30000);
+
+    try {
+      await castVote(fillers[MAX_PROPOSAL_VOTERS - 1].node, proposal.publicKey);
+      assert.fail("a vote beyond the voter cap should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalFull");
+    }
+
+    // With more active nodes than voter slots, a unanimous threshold could
+    // never be met
+    const setThreshold = (bps: number) =>
+      program.methods
+        .setConsensusThreshold(new anchor.BN(bps))
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    const setStakeWeighted = (stakeWeighted: boolean) =>
+      program.methods
+        .setStakeWeighted(stakeWeighted)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    try {
+      await setThreshold(10000);
+      assert.fail("a threshold beyond the voter cap should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "VoterCapacityExceeded");
+    }
+
+    // Stake-weighted quorum doesn't count nodes, so the cap only applies
+    // when switching back to node-count consensus
+    await setStakeWeighted(true);
+    await setThreshold(10000);
+    try {
+      await setStakeWeighted(false);
+      assert.fail("node-count consensus should be refused beyond the voter cap");
+    } catch (err) {
+      assert.include(err.toString(), "VoterCapacityExceeded");
+    }
+    await setThreshold(6700);
+    await setStakeWeighted(false);
+
+    // Take the filler nodes back out of the consensus denominator
+    for (const { node, nodeState } of fillers) {
+      await program.methods
+        .setNodePaused(true)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState,
+          owner: node.publicKey,
+        })
+        .signers([node])
+        .rpc();
+    }
+    await setMinRegions(0);
+  });
+
+  it("Returns MathOverflow instead of underflowing stake", async () => {
+    const node = nodeStates[1];
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [protocolState.publicKey.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .updateStake(new anchor.BN(1500001), false)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState: await findNodeState(node.publicKey),
+          staker: node.publicKey,
+          stakerTokenAccount: userTokenAccounts[1],
+          protocolVault,
+          vaultAuthority,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([node])
+        .rpc();
+      assert.fail("withdrawing more than the stake should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "MathOverflow");
+    }
+  });
+
+  it("Doesn't let a solo proposer finalize its own proposal", async () => {
+    const proposer = nodeStates[2];
+    const other = nodeStates[1];
+    const otherState = await findNodeState(other.publicKey);
+    const proposal = anchor.web3.Keypair.generate();
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .setNodePaused(paused)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState: otherState,
+          owner: other.publicKey,
+        })
+        .signers([other])
+        .rpc();
+
+    // With the other node paused the proposer alone is 100% of active nodes
+    await setPaused(true);
+    await proposeRoot(proposer, proposal, Buffer.alloc(32, 12));
+    await program.methods
+      .reevaluateProposal()
+      .accounts({
+        protocolState: protocolState.publicKey,
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey),
+      })
+      .rpc();
+
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.consensusReached, 0);
+
+    await setPaused(false);
+  });
+
+  it("Raises reputation for voters aligned with an executed proposal", async () => {
+    const proposer = nodeStates[2];
+    const voter = nodeStates[1];
+    const voterState = await findNodeState(voter.publicKey);
+    const proposal = anchor.web3.Keypair.generate();
+    const updateReputations = () =>
+      program.methods
+        .updateReputations()
+        .accounts({
+          protocolState: protocolState.publicKey,
+          proposal: proposal.publicKey,
+        })
+        .remainingAccounts([{ pubkey: voterState, isWritable: true, isSigner: false }])
+        .rpc();
+
+    await proposeRoot(proposer, proposal, Buffer.alloc(32, 13));
+    await castVote(voter, proposal.publicKey);
+
+    const state = await program.account.protocolState.fetch(protocolState.publicKey);
+    const before = await program.account.nodeState.fetch(voterState);
+
+    await updateReputations();
+    let after = await program.account.nodeState.fetch(voterState);
+    assert.equal(
+      after.reputation.toString(),
+      before.reputation.add(state.reputationReward).toString()
+    );
+
+    // A second settlement of the same proposal doesn't credit again
+    await updateReputations();
+    after = await program.account.nodeState.fetch(voterState);
+    assert.equal(
+      after.reputation.toString(),
+      before.reputation.add(state.reputationReward).toString()
+    );
+  });
+
+  it("Slashes a node and deactivates it below min_stake", async () => {
+    const { nodeState } = await registerFreshNode();
+
+    await program.methods
+      .setSlashParams(new anchor.BN(5000), new anchor.BN(100))
+      .accounts({
+        protocolState: protocolState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .slashNode(1)
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState,
+        proposal: null,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.stake.toString(), "750000");
+    assert.equal(state.reputation.toString(), "900");
+    assert.equal(state.isActive, false);
+  });
+
+  it("Slashes a deregistered node's stake during its unstake cooldown", async () => {
+    const exitState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: exitState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([exitState])
+      .rpc();
+    await program.methods
+      .setSlashParams(new anchor.BN(5000), new anchor.BN(100))
+      .accounts({
+        protocolState: exitState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+    await setUnstakeCooldown(3600, exitState.publicKey);
+    const { node, tokenAccount, nodeState } = await registerFreshNode(
+      undefined,
+      0,
+      exitState.publicKey
+    );
+
+    // Leaving right after misbehaving doesn't take the stake out of reach
+    await program.methods
+      .deregisterNode()
+      .accounts({
+        protocolState: exitState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+      })
+      .signers([node])
+      .rpc();
+    await program.methods
+      .slashNode(1)
+      .accounts({
+        protocolState: exitState.publicKey,
+        nodeState,
+        proposal: null,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.stake.toString(), "0");
+    assert.equal(state.pendingUnstake.toString(), "750000");
+    assert.equal(state.reputation.toString(), "900");
+    try {
+      await claimUnstake(node, tokenAccount, exitState.publicKey);
+      assert.fail("the slashed remainder is still in its cooldown");
+    } catch (err) {
+      assert.include(err.toString(), "CooldownNotElapsed");
+    }
+  });
+
+  it("Rejects epoch advancement before the duration elapses", async () => {
+    try {
+      await program.methods
+        .advanceEpoch()
+        .accounts({ protocolState: protocolState.publicKey })
+        .rpc();
+      assert.fail("advancing early should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "EpochNotElapsed");
+    }
+  });
+
+  it("Advances the epoch once the duration elapses", async () => {
+    // Separate protocol instance with a one-second epoch
+    const shortEpochState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, new anchor.BN(1))
+      .accounts({
+        protocolState: shortEpochState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([shortEpochState])
+      .rpc();
+
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    await program.methods
+      .advanceEpoch()
+      .accounts({ protocolState: shortEpochState.publicKey })
+      .rpc();
+
+    const state = await program.account.protocolState.fetch(shortEpochState.publicKey);
+    assert.equal(state.epoch.toString(), "1");
+  });
+
+  it("Holds unstaked tokens until the cooldown elapses", async () => {
+    const node = nodeStates[1];
+    const nodeState = await findNodeState(node.publicKey);
+    const before = (await getAccount(provider.connection, userTokenAccounts[1])).amount;
+
+    await setUnstakeCooldown(2);
+
+    await program.methods
+      .requestUnstake(new anchor.BN(100000))
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+      })
+      .signers([node])
+      .rpc();
+
+    try {
+      await claimUnstake(node, userTokenAccounts[1]);
+      assert.fail("claims inside the cooldown should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "CooldownNotElapsed");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await claimUnstake(node, userTokenAccounts[1]);
+
+    const after = (await getAccount(provider.connection, userTokenAccounts[1])).amount;
+    const state = await program.account.nodeState.fetch(nodeState);
+    assert.equal((after - before).toString(), "100000");
+    assert.equal(state.pendingUnstake.toString(), "0");
+
+    await setUnstakeCooldown(0);
+  });
+
+  it("Auto-deactivates nodes that stop sending heartbeats", async () => {
+    // Separate protocol instance with a one-second epoch
+    const livenessState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, new anchor.BN(1))
+      .accounts({
+        protocolState: livenessState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([livenessState])
+      .rpc();
+    await program.methods
+      .setMaxMissedEpochs(new anchor.BN(2))
+      .accounts({
+        protocolState: livenessState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const live = await registerFreshNode(undefined, 0, livenessState.publicKey);
+    const idle = await registerFreshNode(undefined, 0, livenessState.publicKey);
+
+    for (let i = 0; i < 3; i++) {
+      await program.methods
+        .heartbeat(CLIENT_VERSION)
+        .accounts({
+          protocolState: livenessState.publicKey,
+          nodeState: live.nodeState,
+          owner: live.node.publicKey,
+        })
+        .signers([live.node])
+        .rpc();
+
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      await program.methods
+        .advanceEpoch()
+        .accounts({ protocolState: livenessState.publicKey })
+        .remainingAccounts(
+          [live.nodeState, idle.nodeState].map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+    }
+
+    const liveState = await program.account.nodeState.fetch(live.nodeState);
+    const idleState = await program.account.nodeState.fetch(idle.nodeState);
+    const protocol = await program.account.protocolState.fetch(livenessState.publicKey);
+    assert.equal(liveState.isPaused, false);
+    assert.equal(liveState.missedEpochs.toString(), "0");
+    assert.equal(idleState.isPaused, true);
+    assert.equal(idleState.missedEpochs.toString(), "2");
+    assert.equal(protocol.activeNodeCount.toString(), "1");
+  });
+
+  it("Registers observers without touching consensus counts", async () => {
+    const observer = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        observer.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      )
+    );
+    const findObserver = async (state: anchor.web3.PublicKey) =>
+      (
+        await anchor.web3.PublicKey.findProgramAddress(
+          [Buffer.from("observer"), state.toBuffer(), observer.publicKey.toBuffer()],
+          program.programId
+        )
+      )[0];
+    const registerObserver = async (state: anchor.web3.PublicKey) =>
+      program.methods
+        .registerObserver()
+        .accounts({
+          protocolState: state,
+          observer: await findObserver(state),
+          owner: observer.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([observer])
+        .rpc();
+    const observerAccount = await findObserver(protocolState.publicKey);
+    const before = await program.account.protocolState.fetch(protocolState.publicKey);
+
+    await registerObserver(protocolState.publicKey);
+
+    const after = await program.account.protocolState.fetch(protocolState.publicKey);
+    const state = await program.account.observer.fetch(observerAccount);
+    assert.ok(state.owner.equals(observer.publicKey));
+    assert.equal(after.activeNodeCount.toString(), before.activeNodeCount.toString());
+
+    // Without a node account the observer has nothing to vote with
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(nodeStates[2], proposal, Buffer.alloc(32, 9));
+    try {
+      await castVote(observer, proposal.publicKey);
+      assert.fail("observers should not be able to vote");
+    } catch (err) {
+      assert.include(err.toString(), "AccountNotInitialized");
+    }
+
+    // The same owner can observe another protocol instance as well
+    const otherState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: otherState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([otherState])
+      .rpc();
+    await registerObserver(otherState.publicKey);
+    const other = await program.account.observer.fetch(await findObserver(otherState.publicKey));
+    assert.ok(other.protocolState.equals(otherState.publicKey));
+  });
+
+  it("Weighs votes by stake when stake-weighted consensus is enabled", async () => {
+    const weightedState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: weightedState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([weightedState])
+      .rpc();
+
+    // One large staker and three minimum-sized nodes
+    await registerFreshNode(new anchor.BN(10000000), 0, weightedState.publicKey);
+    const small = [];
+    for (let i = 0; i < 3; i++) {
+      small.push(await registerFreshNode(undefined, 0, weightedState.publicKey));
+    }
+
+    // The three small nodes hold 3 of 4 nodes but under a third of the stake
+    const approveWithSmallNodes = async (root: Buffer) => {
+      const proposal = anchor.web3.Keypair.generate();
+      await proposeRoot(small[0].node, proposal, root, undefined, weightedState.publicKey);
+      await castVote(small[1].node, proposal.publicKey, weightedState.publicKey);
+      await castVote(small[2].node, proposal.publicKey, weightedState.publicKey);
+      return program.account.proposal.fetch(proposal.publicKey);
+    };
+
+    const byCount = await approveWithSmallNodes(Buffer.alloc(32, 10));
+    assert.equal(byCount.consensusReached, 1);
+
+    await program.methods
+      .setStakeWeighted(true)
+      .accounts({
+        protocolState: weightedState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const byStake = await approveWithSmallNodes(Buffer.alloc(32, 11));
+    const state = await program.account.protocolState.fetch(weightedState.publicKey);
+    assert.equal(state.totalActiveStake.toString(), "14500000");
+    assert.equal(byStake.totalStakeVoted.toString(), "4500000");
+    assert.equal(byStake.consensusReached, 0);
+  });
+
+  it("Reduces a recent proposer's voting weight until it recovers", async () => {
+    // Separate protocol instance with a one-second epoch
+    const penaltyState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, new anchor.BN(1))
+      .accounts({
+        protocolState: penaltyState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([penaltyState])
+      .rpc();
+    await program.methods
+      .setRecentProposerPenalty(new anchor.BN(2), new anchor.BN(5000))
+      .accounts({
+        protocolState: penaltyState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const [proposer, voterA, voterB] = [
+      await registerFreshNode(undefined, 0, penaltyState.publicKey),
+      await registerFreshNode(undefined, 0, penaltyState.publicKey),
+      await registerFreshNode(undefined, 0, penaltyState.publicKey),
+    ];
+
+    // Get a proposal from `proposer` executed; execution starts the penalty
+    const accepted = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      proposer.node,
+      accepted,
+      Buffer.alloc(32, 12),
+      undefined,
+      penaltyState.publicKey
+    );
+    await castVote(voterA.node, accepted.publicKey, penaltyState.publicKey);
+    await castVote(voterB.node, accepted.publicKey, penaltyState.publicKey);
+    const credited = await program.account.nodeState.fetch(proposer.nodeState);
+    assert.equal(credited.lastSuccessfulProposalEpoch.toString(), "0");
+
+    // Stake voted on a fresh proposal by voterA (1.5M) and the proposer
+    const weightVoted = async (root: Buffer) => {
+      const proposal = anchor.web3.Keypair.generate();
+      await proposeRoot(voterA.node, proposal, root, undefined, penaltyState.publicKey);
+      await castVote(proposer.node, proposal.publicKey, penaltyState.publicKey);
+      return (await program.account.proposal.fetch(proposal.publicKey)).totalStakeVoted.toString();
+    };
+
+    // Half weight right after the success
+    assert.equal(await weightVoted(Buffer.alloc(32, 13)), "2250000");
+
+    for (let i = 0; i < 2; i++) {
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      await program.methods
+        .advanceEpoch()
+        .accounts({ protocolState: penaltyState.publicKey })
+        .rpc();
+    }
+
+    // Full weight once recent_proposer_epochs have passed
+    assert.equal(await weightVoted(Buffer.alloc(32, 14)), "3000000");
+  });
+
+  it("Rejects votes once a proposal expires", async () => {
+    const proposer = nodeStates[2];
+    const voter = nodeStates[1];
+    const setProposalTtl = (seconds: number) =>
+      program.methods
+        .setProposalTtl(new anchor.BN(seconds))
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    await setProposalTtl(2);
+
+    // Just before expiry the vote lands
+    const fresh = anchor.web3.Keypair.generate();
+    await proposeRoot(proposer, fresh, Buffer.alloc(32, 15));
+    await castVote(voter, fresh.publicKey);
+
+    // Just after expiry it doesn't
+    const stale = anchor.web3.Keypair.generate();
+    await proposeRoot(proposer, stale, Buffer.alloc(32, 16));
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    try {
+      await castVote(voter, stale.publicKey);
+      assert.fail("votes after expiry should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalExpired");
+    }
+
+    await program.methods
+      .expireProposal()
+      .accounts({ protocolState: protocolState.publicKey, proposal: stale.publicKey })
+      .rpc();
+    const proposalState = await program.account.proposal.fetch(stale.publicKey);
+    assert.equal(proposalState.expired, 1);
+
+    await setProposalTtl(0);
+  });
+
+  it("Bars nodes below the minimum client version from proposing", async () => {
+    const { node, nodeState } = await registerFreshNode();
+    const setMinClientVersion = (version: number) =>
+      program.methods
+        .setMinClientVersion(version)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    await setMinClientVersion(CLIENT_VERSION + 1);
+
+    try {
+      await proposeRoot(node, anchor.web3.Keypair.generate(), Buffer.alloc(32, 17));
+      assert.fail("outdated clients should not be able to propose");
+    } catch (err) {
+      assert.include(err.toString(), "ClientVersionTooOld");
+    }
+
+    // Upgrading and announcing the new version via heartbeat restores access
+    await program.methods
+      .heartbeat(CLIENT_VERSION + 1)
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState,
+        owner: node.publicKey,
+      })
+      .signers([node])
+      .rpc();
+    await proposeRoot(node, anchor.web3.Keypair.generate(), Buffer.alloc(32, 17));
+
+    await setMinClientVersion(0);
+  });
+
+  it("Rejects a proposal once enough nodes vote against it", async () => {
+    const rejectionState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: rejectionState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([rejectionState])
+      .rpc();
+
+    const [proposer, voterA, voterB] = [
+      await registerFreshNode(undefined, 0, rejectionState.publicKey),
+      await registerFreshNode(undefined, 0, rejectionState.publicKey),
+      await registerFreshNode(undefined, 0, rejectionState.publicKey),
+    ];
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      proposer.node,
+      proposal,
+      Buffer.alloc(32, 18),
+      undefined,
+      rejectionState.publicKey
+    );
+
+    await castVote(voterA.node, proposal.publicKey, rejectionState.publicKey, false);
+
+    // A node can't vote again, even in the other direction
+    try {
+      await castVote(voterA.node, proposal.publicKey, rejectionState.publicKey, true);
+      assert.fail("a second vote should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "AlreadyVoted");
+    }
+
+    // Two of three nodes against crosses the default 34% rejection threshold
+    await castVote(voterB.node, proposal.publicKey, rejectionState.publicKey, false);
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.rejected, 1);
+    assert.equal(proposalState.noVoteCount.toString(), "2");
+    assert.equal(proposalState.executed, 0);
+
+    try {
+      await program.methods
+        .executeProposal()
+        .accounts({
+          protocolState: rejectionState.publicKey,
+          proposal: proposal.publicKey,
+          proposerState: await proposerStateOf(proposal.publicKey, rejectionState.publicKey),
+          executor: provider.wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("rejected proposals should not execute");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalRejected");
+    }
+  });
+
+  it("Hands over admin in two steps and supports renouncing it", async () => {
+    const adminState = anchor.web3.Keypair.generate();
+    const newAdmin = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: adminState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([adminState])
+      .rpc();
+    const setProposalTtl = (admin: anchor.web3.Keypair | null) => {
+      const builder = program.methods.setProposalTtl(new anchor.BN(0)).accounts({
+        protocolState: adminState.publicKey,
+        admin: admin ? admin.publicKey : provider.wallet.publicKey,
+      });
+      return admin ? builder.signers([admin]).rpc() : builder.rpc();
+    };
+
+    await program.methods
+      .transferAdmin(newAdmin.publicKey)
+      .accounts({
+        protocolState: adminState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // Nomination alone grants nothing
+    try {
+      await setProposalTtl(newAdmin);
+      assert.fail("a pending admin should not have privileges yet");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+
+    await program.methods
+      .acceptAdmin()
+      .accounts({
+        protocolState: adminState.publicKey,
+        newAdmin: newAdmin.publicKey,
+      })
+      .signers([newAdmin])
+      .rpc();
+
+    let state = await program.account.protocolState.fetch(adminState.publicKey);
+    assert.ok(state.admin.equals(newAdmin.publicKey));
+    assert.isNull(state.pendingAdmin);
+
+    try {
+      await setProposalTtl(null);
+      assert.fail("the old admin should lose privileges after acceptance");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+    await setProposalTtl(newAdmin);
+
+    await program.methods
+      .renounceAdmin()
+      .accounts({
+        protocolState: adminState.publicKey,
+        admin: newAdmin.publicKey,
+      })
+      .signers([newAdmin])
+      .rpc();
+
+    state = await program.account.protocolState.fetch(adminState.publicKey);
+    assert.ok(state.admin.equals(anchor.web3.PublicKey.default));
+    try {
+      await setProposalTtl(newAdmin);
+      assert.fail("no one should hold admin after renouncing");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+  });
+
+  it("Halts everything but exits while the protocol is paused", async () => {
+    const exiting = await registerFreshNode();
+    const voter = nodeStates[1];
+    const openProposal = anchor.web3.Keypair.generate();
+    await proposeRoot(nodeStates[2], openProposal, Buffer.alloc(32, 19));
+    const setPaused = (paused: boolean) =>
+      program.methods
+        .setPaused(paused)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    const expectPaused = async (action: Promise<unknown>) => {
+      try {
+        await action;
+        assert.fail("guarded instructions should revert while paused");
+      } catch (err) {
+        assert.include(err.toString(), "ProtocolPaused");
+      }
+    };
+
+    await setPaused(true);
+
+    await expectPaused(registerFreshNode());
+    await expectPaused(
+      proposeRoot(nodeStates[2], anchor.web3.Keypair.generate(), Buffer.alloc(32, 20))
+    );
+    await expectPaused(castVote(voter, openProposal.publicKey));
+    await expectPaused(
+      program.methods
+        .updateStake(new anchor.BN(1), true)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState: exiting.nodeState,
+          staker: exiting.node.publicKey,
+          stakerTokenAccount: exiting.tokenAccount,
+          protocolVault,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([exiting.node])
+        .rpc()
+    );
+
+    // Exits stay open
+    await program.methods
+      .deregisterNode()
+      .accounts({
+        protocolState: protocolState.publicKey,
+        nodeState: exiting.nodeState,
+        staker: exiting.node.publicKey,
+      })
+      .signers([exiting.node])
+      .rpc();
+    await claimUnstake(exiting.node, exiting.tokenAccount);
+
+    const state = await program.account.nodeState.fetch(exiting.nodeState);
+    assert.equal(state.isActive, false);
+    assert.equal(state.pendingUnstake.toString(), "0");
+
+    await setPaused(false);
+  });
+
+  it("Scores decentralization higher for spread-out, evenly staked nodes", async () => {
+    const scoreState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: scoreState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([scoreState])
+      .rpc();
+    const score = async (nodes: { nodeState: anchor.web3.PublicKey }[]) => {
+      const { events } = await program.methods
+        .emitDecentralizationScore()
+        .accounts({ protocolState: scoreState.publicKey })
+        .remainingAccounts(
+          nodes.map(({ nodeState }) => ({
+            pubkey: nodeState,
+            isWritable: false,
+            isSigner: false,
+          }))
+        )
+        .simulate();
+      return events.find((e) => e.name === "DecentralizationScore").data;
+    };
+
+    // Equal stakes across three regions
+    const spread = [];
+    for (const region of [1, 2, 3]) {
+      spread.push(await registerFreshNode(undefined, region, scoreState.publicKey));
+    }
+    // One dominant staker, everyone in a single region
+    const concentrated = [
+      await registerFreshNode(new anchor.BN(20000000), 0, scoreState.publicKey),
+      await registerFreshNode(undefined, 0, scoreState.publicKey),
+      await registerFreshNode(undefined, 0, scoreState.publicKey),
+    ];
+
+    const spreadScore = await score(spread);
+    const concentratedScore = await score(concentrated);
+    assert.equal(spreadScore.giniBps.toString(), "0");
+    assert.equal(spreadScore.distinctRegions.toString(), "3");
+    assert.equal(concentratedScore.distinctRegions.toString(), "1");
+    assert.isTrue(
+      spreadScore.decentralizationScore.gt(concentratedScore.decentralizationScore)
+    );
+
+    // More nodes raise the size component
+    const combinedScore = await score([...spread, ...concentrated]);
+    assert.isTrue(
+      combinedScore.decentralizationScore.gt(concentratedScore.decentralizationScore)
+    );
+  });
+
+  it("Withholds reputation from quorate proposals below the participation floor", async () => {
+    const floorState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: floorState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([floorState])
+      .rpc();
+    await program.methods
+      .setReputationParticipationFloor(new anchor.BN(9000))
+      .accounts({
+        protocolState: floorState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const nodes = [];
+    for (let i = 0; i < 4; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, floorState.publicKey));
+    }
+
+    // Three of four nodes clear the 67% quorum but not the 90% floor
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      nodes[0].node,
+      proposal,
+      Buffer.alloc(32, 21),
+      undefined,
+      floorState.publicKey
+    );
+    await castVote(nodes[1].node, proposal.publicKey, floorState.publicKey);
+    await castVote(nodes[2].node, proposal.publicKey, floorState.publicKey);
+
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.executed, 1);
+    assert.equal(proposalState.participationBps.toString(), "7500");
+
+    const before = await program.account.nodeState.fetch(nodes[1].nodeState);
+    try {
+      await program.methods
+        .updateReputations()
+        .accounts({
+          protocolState: floorState.publicKey,
+          proposal: proposal.publicKey,
+        })
+        .remainingAccounts([{ pubkey: nodes[1].nodeState, isWritable: true, isSigner: false }])
+        .rpc();
+      assert.fail("low-participation proposals should not pay out reputation");
+    } catch (err) {
+      assert.include(err.toString(), "ParticipationBelowFloor");
+    }
+
+    // A state without the floor can't stand in for the proposal's own
+    const lenientState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: lenientState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lenientState])
+      .rpc();
+    try {
+      await program.methods
+        .updateReputations()
+        .accounts({
+          protocolState: lenientState.publicKey,
+          proposal: proposal.publicKey,
+        })
+        .remainingAccounts([{ pubkey: nodes[1].nodeState, isWritable: true, isSigner: false }])
+        .rpc();
+      assert.fail("a foreign protocol state should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidParameter");
+    }
+    const after = await program.account.nodeState.fetch(nodes[1].nodeState);
+    assert.equal(after.reputation.toString(), before.reputation.toString());
+  });
+
+  it("Applies the configured consensus threshold", async () => {
+    const thresholdState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: thresholdState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([thresholdState])
+      .rpc();
+    const setThreshold = (bps: number) =>
+      program.methods
+        .setConsensusThreshold(new anchor.BN(bps))
+        .accounts({
+          protocolState: thresholdState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    try {
+      await setThreshold(4999);
+      assert.fail("thresholds below a simple majority should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidParameter");
+    }
+
+    const nodes = [];
+    for (let i = 0; i < 3; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, thresholdState.publicKey));
+    }
+
+    // Two of three approvals fall just short of the default 67%
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      nodes[0].node,
+      proposal,
+      Buffer.alloc(32, 22),
+      undefined,
+      thresholdState.publicKey
+    );
+    await castVote(nodes[1].node, proposal.publicKey, thresholdState.publicKey);
+    let proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.consensusReached, 0);
+
+    // The same votes clear a 60% threshold
+    await setThreshold(6000);
+    await program.methods
+      .reevaluateProposal()
+      .accounts({
+        protocolState: thresholdState.publicKey,
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey, thresholdState.publicKey),
+      })
+      .rpc();
+    proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.consensusReached, 1);
+  });
+
+  it("Updates min_stake and epoch_duration through governance", async () => {
+    const paramsState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: paramsState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([paramsState])
+      .rpc();
+    const existing = await registerFreshNode(undefined, 0, paramsState.publicKey);
+    const updateParams = (minStake: number, epochDuration: number) =>
+      program.methods
+        .updateProtocolParams(new anchor.BN(minStake), new anchor.BN(epochDuration))
+        .accounts({
+          protocolState: paramsState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+
+    try {
+      await updateParams(2000000, 0);
+      assert.fail("a zero epoch duration should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidParameter");
+    }
+
+    await updateParams(2000000, 600);
+    const state = await program.account.protocolState.fetch(paramsState.publicKey);
+    assert.equal(state.minStake.toString(), "2000000");
+    assert.equal(state.epochDuration.toString(), "600");
+
+    try {
+      await registerFreshNode(new anchor.BN(1500000), 0, paramsState.publicKey);
+      assert.fail("registrations below the new min_stake should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InsufficientStake");
+    }
+    await registerFreshNode(new anchor.BN(2000000), 0, paramsState.publicKey);
+
+    // The existing node isn't slashed, only deactivated once it interacts
+    await program.methods
+      .heartbeat(CLIENT_VERSION)
+      .accounts({
+        protocolState: paramsState.publicKey,
+        nodeState: existing.nodeState,
+        owner: existing.node.publicKey,
+      })
+      .signers([existing.node])
+      .rpc();
+    const node = await program.account.nodeState.fetch(existing.nodeState);
+    assert.equal(node.isActive, false);
+    assert.equal(node.stake.toString(), "1500000");
+  });
+
+  it("Decays reputation for nodes idle across epochs", async () => {
+    // Separate protocol instance with a one-second epoch
+    const decayState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, new anchor.BN(1))
+      .accounts({
+        protocolState: decayState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([decayState])
+      .rpc();
+    await program.methods
+      .setReputationDecay(new anchor.BN(1000), new anchor.BN(500))
+      .accounts({
+        protocolState: decayState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const active = await registerFreshNode(undefined, 0, decayState.publicKey);
+    const idle = await registerFreshNode(undefined, 0, decayState.publicKey);
+    // Left out of every epoch advance but the last
+    const skipped = await registerFreshNode(undefined, 0, decayState.publicKey);
+
+    for (let i = 0; i < 3; i++) {
+      const passed = [active.nodeState, idle.nodeState];
+      if (i === 2) {
+        passed.push(skipped.nodeState);
+      }
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      await program.methods
+        .heartbeat(CLIENT_VERSION)
+        .accounts({
+          protocolState: decayState.publicKey,
+          nodeState: active.nodeState,
+          owner: active.node.publicKey,
+        })
+        .signers([active.node])
+        .rpc();
+      await program.methods
+        .advanceEpoch()
+        .accounts({ protocolState: decayState.publicKey })
+        .remainingAccounts(
+          passed.map((pubkey) => ({
+            pubkey,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+    }
+
+    const activeState = await program.account.nodeState.fetch(active.nodeState);
+    const idleState = await program.account.nodeState.fetch(idle.nodeState);
+    assert.equal(activeState.reputation.toString(), "1000");
+    assert.equal(activeState.epochsInactive.toString(), "0");
+    // Registration counted as activity in the first epoch, then two idle
+    // epochs at 10% and 20%: 1000 -> 900 -> 720
+    assert.equal(idleState.epochsInactive.toString(), "2");
+    assert.equal(idleState.reputation.toString(), "720");
+    // Leaving a node out only defers its decay
+    const skippedState = await program.account.nodeState.fetch(skipped.nodeState);
+    assert.equal(skippedState.epochsInactive.toString(), "2");
+    assert.equal(skippedState.reputation.toString(), "720");
+  });
+
+  it("Reactivates a deregistered node on its existing account", async () => {
+    const cycleState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: cycleState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([cycleState])
+      .rpc();
+    const activeCount = async () =>
+      (
+        await program.account.protocolState.fetch(cycleState.publicKey)
+      ).activeNodeCount.toString();
+
+    const { node, tokenAccount, nodeState } = await registerFreshNode(
+      undefined,
+      0,
+      cycleState.publicKey
+    );
+    assert.equal(await activeCount(), "1");
+
+    await program.methods
+      .deregisterNode()
+      .accounts({
+        protocolState: cycleState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+      })
+      .signers([node])
+      .rpc();
+    assert.equal(await activeCount(), "0");
+
+    // register_node can't reuse the PDA, reactivate_node can
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      tokenAccount,
+      provider.wallet.payer,
+      1500000
+    );
+    try {
+      await program.methods
+        .registerNode(
+          new anchor.BN(1500000),
+          Buffer.alloc(32, 2),
+          1,
+          CLIENT_VERSION,
+          "",
+          Buffer.alloc(32)
+        )
+        .accounts({
+          protocolState: cycleState.publicKey,
+          nodeState,
+          staker: node.publicKey,
+          stakerTokenAccount: tokenAccount,
+          protocolVault: await vaultFor(cycleState.publicKey),
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([node])
+        .rpc();
+      assert.fail("register_node should not reinitialize an existing node account");
+    } catch (err) {
+      assert.include((err.logs ?? []).join("\n"), "already in use");
+    }
+    await program.methods
+      .reactivateNode(new anchor.BN(1500000), Buffer.alloc(32, 2), 1, CLIENT_VERSION)
+      .accounts({
+        protocolState: cycleState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+        stakerTokenAccount: tokenAccount,
+        protocolVault: await vaultFor(cycleState.publicKey),
+        stakeMint: synapseToken,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([node])
+      .rpc();
+
+    const state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.isActive, true);
+    assert.equal(state.stake.toString(), "1500000");
+    assert.equal(state.region, 1);
+    assert.equal(await activeCount(), "1");
+  });
+
+  it("Distributes epoch rewards pro rata to active stake", async () => {
+    const rewardState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, new anchor.BN(1))
+      .accounts({
+        protocolState: rewardState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([rewardState])
+      .rpc();
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [rewardState.publicKey.toBuffer()],
+      program.programId
+    );
+    const rewardPool = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      vaultAuthority,
+      anchor.web3.Keypair.generate()
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      rewardPool,
+      provider.wallet.payer,
+      400000
+    );
+    await program.methods
+      .setRewardParams(new anchor.BN(400000))
+      .accounts({
+        protocolState: rewardState.publicKey,
+        admin: provider.wallet.publicKey,
+        rewardPool,
+        vaultAuthority,
+      })
+      .rpc();
+
+    const small = await registerFreshNode(new anchor.BN(1000000), 0, rewardState.publicKey);
+    const large = await registerFreshNode(new anchor.BN(3000000), 0, rewardState.publicKey);
+
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    await program.methods
+      .advanceEpoch()
+      .accounts({ protocolState: rewardState.publicKey })
+      .rpc();
+
+    const distribute = () =>
+      program.methods
+        .distributeRewards()
+        .accounts({ protocolState: rewardState.publicKey, rewardPool })
+        .rpc();
+    await distribute();
+    try {
+      await distribute();
+      assert.fail("an epoch should only be rewarded once");
+    } catch (err) {
+      assert.include(err.toString(), "EpochAlreadyRewarded");
+    }
+
+    const claim = ({ node, tokenAccount, nodeState }) =>
+      program.methods
+        .claimReward()
+        .accounts({
+          protocolState: rewardState.publicKey,
+          nodeState,
+          staker: node.publicKey,
+          stakerTokenAccount: tokenAccount,
+          rewardPool,
+          vaultAuthority,
+          rewardMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([node])
+        .rpc();
+    await claim(small);
+    await claim(large);
+
+    assert.equal(
+      (await getAccount(provider.connection, small.tokenAccount)).amount,
+      BigInt(100000)
+    );
+    assert.equal(
+      (await getAccount(provider.connection, large.tokenAccount)).amount,
+      BigInt(300000)
+    );
+    const state = await program.account.protocolState.fetch(rewardState.publicKey);
+    assert.equal(state.rewardLiabilities.toString(), "0");
+
+    try {
+      await claim(small);
+      assert.fail("nothing should be left to claim");
+    } catch (err) {
+      assert.include(err.toString(), "NothingToClaim");
+    }
+  });
+
+  it("Only credits reputation for votes spaced beyond the interval", async () => {
+    const spacingState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: spacingState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([spacingState])
+      .rpc();
+    await program.methods
+      .setMinReputationVoteInterval(new anchor.BN(3600))
+      .accounts({
+        protocolState: spacingState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const nodes = [];
+    for (let i = 0; i < 3; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, spacingState.publicKey));
+    }
+
+    // Two back-to-back proposals, both reaching consensus
+    const proposals = [anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate()];
+    for (const [i, proposal] of proposals.entries()) {
+      await proposeRoot(
+        nodes[0].node,
+        proposal,
+        Buffer.alloc(32, 30 + i),
+        undefined,
+        spacingState.publicKey
+      );
+      await castVote(nodes[1].node, proposal.publicKey, spacingState.publicKey);
+      await castVote(nodes[2].node, proposal.publicKey, spacingState.publicKey);
+
+      const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+      assert.equal(proposalState.executed, 1);
+    }
+    const second = await program.account.proposal.fetch(proposals[1].publicKey);
+    assert.isTrue(second.reputationEligible.every((byte: number) => byte === 0));
+
+    for (const proposal of proposals) {
+      await program.methods
+        .updateReputations()
+        .accounts({
+          protocolState: spacingState.publicKey,
+          proposal: proposal.publicKey,
+        })
+        .remainingAccounts([{ pubkey: nodes[1].nodeState, isWritable: true, isSigner: false }])
+        .rpc();
+    }
+
+    // Only the first vote earned the 10-point reward
+    const voter = await program.account.nodeState.fetch(nodes[1].nodeState);
+    assert.equal(voter.reputation.toString(), "1010");
+  });
+
+  it("Redistributes slashed stake to voters who opposed the slashed node", async () => {
+    const slashState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: slashState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([slashState])
+      .rpc();
+
+    const honest = [
+      await registerFreshNode(new anchor.BN(1000000), 0, slashState.publicKey),
+      await registerFreshNode(new anchor.BN(1000000), 0, slashState.publicKey),
+      await registerFreshNode(new anchor.BN(2000000), 0, slashState.publicKey),
+    ];
+    const bad = await registerFreshNode(undefined, 0, slashState.publicKey);
+
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      honest[0].node,
+      proposal,
+      Buffer.alloc(32, 40),
+      undefined,
+      slashState.publicKey
+    );
+    await castVote(bad.node, proposal.publicKey, slashState.publicKey, false);
+    await castVote(honest[1].node, proposal.publicKey, slashState.publicKey);
+    await castVote(honest[2].node, proposal.publicKey, slashState.publicKey);
+    assert.equal((await program.account.proposal.fetch(proposal.publicKey)).executed, 1);
+
+    // 10% of 1.5M slashed, half of which goes to the honest voters
+    const slash = (proposal: anchor.web3.PublicKey) =>
+      program.methods
+        .slashNode(1)
+        .accounts({
+          protocolState: slashState.publicKey,
+          nodeState: bad.nodeState,
+          proposal,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    await slash(proposal.publicKey);
+
+    // The pending slash is tied to this proposal until it is paid out
+    const other = anchor.web3.Keypair.generate();
+    await proposeRoot(honest[1].node, other, Buffer.alloc(32, 41), undefined, slashState.publicKey);
+    try {
+      await slash(other.publicKey);
+      assert.fail("a second proposal should wait for the pending distribution");
+    } catch (err) {
+      assert.include(err.toString(), "SlashPendingDistribution");
+    }
+
+    // An honest voter that has left since is skipped by passing its closed address
+    const exiting = honest.pop();
+    await setUnstakeCooldown(0, slashState.publicKey);
+    await program.methods
+      .deregisterNode()
+      .accounts({
+        protocolState: slashState.publicKey,
+        nodeState: exiting.nodeState,
+        staker: exiting.node.publicKey,
+      })
+      .signers([exiting.node])
+      .rpc();
+    await claimUnstake(exiting.node, exiting.tokenAccount, slashState.publicKey);
+    await program.methods
+      .setCleanupDelay(new anchor.BN(0))
+      .accounts({
+        protocolState: slashState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+    await program.methods
+      .cleanupInactiveNodes()
+      .accounts({
+        protocolState: slashState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .remainingAccounts([{ pubkey: exiting.nodeState, isWritable: true, isSigner: false }])
+      .rpc();
+
+    // Voters are passed in vote order, a page at a time from `start`
+    const voters = [...honest, exiting];
+    const distribute = (start: number, count: number, decided = proposal.publicKey) =>
+      program.methods
+        .distributeSlashToHonestVoters(start)
+        .accounts({
+          protocolState: slashState.publicKey,
+          slashedNode: bad.nodeState,
+          proposal: decided,
+          admin: provider.wallet.publicKey,
+        })
+        .remainingAccounts(
+          voters.slice(start, start + count).map(({ nodeState }) => ({
+            pubkey: nodeState,
+            isWritable: true,
+            isSigner: false,
+          }))
+        )
+        .rpc();
+    try {
+      await distribute(0, 3, other.publicKey);
+      assert.fail("the slash should only go to voters on its own proposal");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidParameter");
+    }
+
+    // Shares follow ballot weight (1M, 1M, 2M); a repeated page pays nothing
+    const stakes = async () =>
+      Promise.all(
+        honest.map(async ({ nodeState }) =>
+          (await program.account.nodeState.fetch(nodeState)).stake.toString()
+        )
+      );
+    await distribute(0, 1);
+    await distribute(0, 1);
+    assert.deepEqual(await stakes(), ["1018750", "1000000"]);
+    assert.equal(
+      (await program.account.nodeState.fetch(bad.nodeState)).undistributedSlash.toString(),
+      "150000"
+    );
+    await distribute(1, 2);
+    assert.deepEqual(await stakes(), ["1018750", "1018750"]);
+    const state = await program.account.protocolState.fetch(slashState.publicKey);
+    assert.equal(state.totalActiveStake.toString(), "3387500");
+
+    try {
+      await distribute(0, 3);
+      assert.fail("a slash should only be redistributed once");
+    } catch (err) {
+      assert.include(err.toString(), "NoSlashToDistribute");
+    }
+  });
+
+  it("Measures quorum against the active set at proposal creation", async () => {
+    const snapshotState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: snapshotState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([snapshotState])
+      .rpc();
+
+    const nodes = [];
+    for (let i = 0; i < 3; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, snapshotState.publicKey));
+    }
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      nodes[0].node,
+      proposal,
+      Buffer.alloc(32, 50),
+      undefined,
+      snapshotState.publicKey
+    );
+
+    // Late registrations would leave 3 of 5 below the 67% threshold
+    await registerFreshNode(undefined, 0, snapshotState.publicKey);
+    await registerFreshNode(undefined, 0, snapshotState.publicKey);
+    const state = await program.account.protocolState.fetch(snapshotState.publicKey);
+    assert.equal(state.activeNodeCount.toString(), "5");
+
+    await castVote(nodes[1].node, proposal.publicKey, snapshotState.publicKey);
+    await castVote(nodes[2].node, proposal.publicKey, snapshotState.publicKey);
+
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.snapshotNodeCount.toString(), "3");
+    assert.equal(proposalState.snapshotTotalStake.toString(), "4500000");
+    assert.equal(proposalState.executed, 1);
+    assert.equal(proposalState.participationBps.toString(), "10000");
+  });
+
+  it("Requires minimum reputation and stake to propose", async () => {
+    const eligibilityState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: eligibilityState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([eligibilityState])
+      .rpc();
+    const setRequirements = (reputation: number, stake: number) =>
+      program.methods
+        .setProposerRequirements(new anchor.BN(reputation), new anchor.BN(stake))
+        .accounts({
+          protocolState: eligibilityState.publicKey,
+          admin: provider.wallet.publicKey,
+        })
+        .rpc();
+    const { node } = await registerFreshNode(undefined, 0, eligibilityState.publicKey);
+
+    // New nodes start at 1000 reputation
+    await setRequirements(1001, 0);
+    try {
+      await proposeRoot(
+        node,
+        anchor.web3.Keypair.generate(),
+        Buffer.alloc(32, 60),
+        undefined,
+        eligibilityState.publicKey
+      );
+      assert.fail("a node below the reputation bar should not propose");
+    } catch (err) {
+      assert.include(err.toString(), "NotEligibleToPropose");
+    }
+
+    await setRequirements(1000, 2000000);
+    try {
+      await proposeRoot(
+        node,
+        anchor.web3.Keypair.generate(),
+        Buffer.alloc(32, 60),
+        undefined,
+        eligibilityState.publicKey
+      );
+      assert.fail("a node below the stake bar should not propose");
+    } catch (err) {
+      assert.include(err.toString(), "NotEligibleToPropose");
+    }
+
+    await setRequirements(1000, 1500000);
+    await proposeRoot(
+      node,
+      anchor.web3.Keypair.generate(),
+      Buffer.alloc(32, 60),
+      undefined,
+      eligibilityState.publicKey
+    );
+  });
+
+  it("Rate-limits proposals from the same node", async () => {
+    const cooldownState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: cooldownState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([cooldownState])
+      .rpc();
+    await program.methods
+      .setProposalCooldown(new anchor.BN(3))
+      .accounts({
+        protocolState: cooldownState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+    const { node } = await registerFreshNode(undefined, 0, cooldownState.publicKey);
+    let root = 70;
+    const propose = () =>
+      proposeRoot(
+        node,
+        anchor.web3.Keypair.generate(),
+        Buffer.alloc(32, root++),
+        undefined,
+        cooldownState.publicKey
+      );
+
+    await propose();
+    try {
+      await propose();
+      assert.fail("a second proposal inside the cooldown should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalCooldown");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await propose();
+    const state = await program.account.protocolState.fetch(cooldownState.publicKey);
+    assert.equal(state.proposalCount.toString(), "2");
+  });
+
+  it("Checkpoints executed roots in order", async () => {
+    const checkpointState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: checkpointState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([checkpointState])
+      .rpc();
+
+    const nodes = [];
+    for (let i = 0; i < 3; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, checkpointState.publicKey));
+    }
+    const roots = [Buffer.alloc(32, 80), Buffer.alloc(32, 81)];
+    for (const root of roots) {
+      const proposal = anchor.web3.Keypair.generate();
+      await proposeRoot(nodes[0].node, proposal, root, undefined, checkpointState.publicKey);
+      await castVote(nodes[1].node, proposal.publicKey, checkpointState.publicKey);
+      await castVote(nodes[2].node, proposal.publicKey, checkpointState.publicKey);
+    }
+
+    const state = await program.account.protocolState.fetch(checkpointState.publicKey);
+    assert.equal(state.checkpointCount.toString(), "2");
+    assert.deepEqual(
+      state.checkpoints
+        .slice(0, 2)
+        .map((checkpoint) => Buffer.from(checkpoint.neuralStateRoot)),
+      roots
+    );
+    assert.equal(state.checkpoints[1].epoch.toString(), state.epoch.toString());
+  });
+
+  it("Verifies Merkle inclusion against a node's committed root", async () => {
+    const sha256 = (...parts: Buffer[]) => {
+      const hash = createHash("sha256");
+      parts.forEach((part) => hash.update(part));
+      return hash.digest();
+    };
+    const hashLeaf = (leaf: Buffer) => sha256(Buffer.from([0]), leaf);
+    const hashPair = (left: Buffer, right: Buffer) => sha256(Buffer.from([1]), left, right);
+
+    // Four-leaf tree: root = H(H(l0, l1), H(l2, l3))
+    const leaves = [0, 1, 2, 3].map((i) => Buffer.alloc(32, 90 + i));
+    const hashed = leaves.map(hashLeaf);
+    const left = hashPair(hashed[0], hashed[1]);
+    const right = hashPair(hashed[2], hashed[3]);
+    const root = hashPair(left, right);
+
+    const { node, nodeState } = await registerFreshNode();
+    await program.methods
+      .updateNeuralState(root)
+      .accounts({ protocolState: protocolState.publicKey, nodeState, owner: node.publicKey })
+      .signers([node])
+      .rpc();
+    const verify = (leaf: Buffer, index: number, proof: Buffer[]) =>
+      program.methods
+        .verifyInclusion(leaf, index, proof)
+        .accounts({ nodeState })
+        .rpc();
+
+    await verify(leaves[2], 2, [hashed[3], left]);
+    await verify(leaves[1], 1, [hashed[0], right]);
+
+    const corrupted = Buffer.from(hashed[3]);
+    corrupted[0] ^= 1;
+    try {
+      await verify(leaves[2], 2, [corrupted, left]);
+      assert.fail("a corrupted sibling should fail verification");
+    } catch (err) {
+      assert.include(err.toString(), "ProofInvalid");
+    }
+  });
+
+  it("Counts a delegator's weight under its delegate only", async () => {
+    const delegationState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: delegationState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([delegationState])
+      .rpc();
+
+    const nodes = [];
+    for (let i = 0; i < 5; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, delegationState.publicKey));
+    }
+    const [proposer, delegate, delegator] = nodes;
+    await program.methods
+      .setVoteDelegate(delegate.node.publicKey)
+      .accounts({ nodeState: delegator.nodeState, owner: delegator.node.publicKey })
+      .remainingAccounts([{ pubkey: delegate.nodeState, isWritable: false, isSigner: false }])
+      .signers([delegator.node])
+      .rpc();
+
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      proposer.node,
+      proposal,
+      Buffer.alloc(32, 100),
+      undefined,
+      delegationState.publicKey
+    );
+    try {
+      await castVote(delegator.node, proposal.publicKey, delegationState.publicKey);
+      assert.fail("a delegating node should not vote directly");
+    } catch (err) {
+      assert.include(err.toString(), "VoteDelegated");
+    }
+    // Proposing would cast the same weight again through the auto-vote
+    try {
+      await proposeRoot(
+        delegator.node,
+        anchor.web3.Keypair.generate(),
+        Buffer.alloc(32, 101),
+        undefined,
+        delegationState.publicKey
+      );
+      assert.fail("a delegating node should not propose");
+    } catch (err) {
+      assert.include(err.toString(), "VoteDelegated");
+    }
+
+    await program.methods
+      .voteOnProposal(true)
+      .accounts({
+        protocolState: delegationState.publicKey,
+        nodeState: delegate.nodeState,
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey, delegationState.publicKey),
+        voter: delegate.node.publicKey,
+      })
+      .remainingAccounts([{ pubkey: delegator.nodeState, isWritable: false, isSigner: false }])
+      .signers([delegate.node])
+      .rpc();
+
+    // Proposer, delegate and delegator: 3 of 5, short of consensus
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.voteCount.toString(), "3");
+    assert.equal(proposalState.totalStakeVoted.toString(), "4500000");
+    assert.equal(proposalState.executed, 0);
+
+    // Revoking restores the delegator's own vote
+    await program.methods
+      .setVoteDelegate(null)
+      .accounts({ nodeState: delegator.nodeState, owner: delegator.node.publicKey })
+      .signers([delegator.node])
+      .rpc();
+    try {
+      await castVote(delegator.node, proposal.publicKey, delegationState.publicKey);
+      assert.fail("the delegator's weight was already counted");
+    } catch (err) {
+      assert.include(err.toString(), "AlreadyVoted");
+    }
+  });
+
+  it("Stores and updates node metadata within the URI cap", async () => {
+    const { node, nodeState } = await registerFreshNode();
+    const updateMetadata = (uri: string, gossipPubkey: Buffer) =>
+      program.methods
+        .updateNodeMetadata(uri, gossipPubkey)
+        .accounts({ nodeState, owner: node.publicKey })
+        .signers([node])
+        .rpc();
+
+    const uri = "https://node.example.com:7400/gossip";
+    const gossipPubkey = Buffer.alloc(32, 7);
+    await updateMetadata(uri, gossipPubkey);
+
+    let state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.metadataUri, uri);
+    assert.deepEqual(Buffer.from(state.gossipPubkey), gossipPubkey);
+
+    try {
+      await updateMetadata("x".repeat(129), gossipPubkey);
+      assert.fail("URIs over 128 bytes should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "MetadataUriTooLong");
+    }
+    state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.metadataUri, uri);
+  });
+
+  it("Locks in a voter's weight when the vote is cast", async () => {
+    const lockState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: lockState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([lockState])
+      .rpc();
+    await program.methods
+      .setStakeWeighted(true)
+      .accounts({
+        protocolState: lockState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const proposer = await registerFreshNode(undefined, 0, lockState.publicKey);
+    await registerFreshNode(new anchor.BN(10000000), 0, lockState.publicKey);
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      proposer.node,
+      proposal,
+      Buffer.alloc(32, 110),
+      undefined,
+      lockState.publicKey
+    );
+
+    // Topping up after voting doesn't add weight to the cast vote
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      proposer.tokenAccount,
+      provider.wallet.payer,
+      20000000
+    );
+    await program.methods
+      .updateStake(new anchor.BN(20000000), true)
+      .accounts({
+        protocolState: lockState.publicKey,
+        nodeState: proposer.nodeState,
+        staker: proposer.node.publicKey,
+        stakerTokenAccount: proposer.tokenAccount,
+        protocolVault: await vaultFor(lockState.publicKey),
+        stakeMint: synapseToken,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([proposer.node])
+      .rpc();
+    await program.methods
+      .reevaluateProposal()
+      .accounts({
+        protocolState: lockState.publicKey,
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey, lockState.publicKey),
+      })
+      .rpc();
+
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.voterWeights[0].toString(), "1500000");
+    assert.equal(proposalState.totalStakeVoted.toString(), "1500000");
+    assert.equal(proposalState.consensusReached, 0);
+  });
+
+  it("Rejects foreign vaults and mismatched mints", async () => {
+    const { node, nodeState, tokenAccount } = await registerFreshNode();
+    const otherMint = await createMint(
+      provider.connection,
+      provider.wallet.payer,
+      provider.wallet.publicKey,
+      null,
+      9
+    );
+    const foreignTokens = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      otherMint,
+      node.publicKey
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      otherMint,
+      foreignTokens,
+      provider.wallet.payer,
+      1000000
+    );
+    const attackerVault = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      provider.wallet.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+    const increaseStake = (stakerTokenAccount, vault) =>
+      program.methods
+        .updateStake(new anchor.BN(1000), true)
+        .accounts({
+          protocolState: protocolState.publicKey,
+          nodeState,
+          staker: node.publicKey,
+          stakerTokenAccount,
+          protocolVault: vault,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([node])
+        .rpc();
+
+    try {
+      await increaseStake(foreignTokens, protocolVault);
+      assert.fail("stake in another mint should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidMint");
+    }
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      tokenAccount,
+      provider.wallet.payer,
+      1000
+    );
+    try {
+      await increaseStake(tokenAccount, attackerVault);
+      assert.fail("stake sent to a caller-chosen vault should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidVault");
+    }
+
+    // A vault the program doesn't control can't be installed either
+    const fresh = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: fresh.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([fresh])
+      .rpc();
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [fresh.publicKey.toBuffer()],
+      program.programId
+    );
+    try {
+      await program.methods
+        .setProtocolVault()
+        .accounts({
+          protocolState: fresh.publicKey,
+          admin: provider.wallet.publicKey,
+          protocolVault: attackerVault,
+          vaultAuthority,
+        })
+        .rpc();
+      assert.fail("a vault owned by someone else should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidVault");
+    }
+  });
+
+  it("Rejects a second open proposal for the same root", async () => {
+    const duplicateState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: duplicateState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([duplicateState])
+      .rpc();
+    const first = await registerFreshNode(undefined, 0, duplicateState.publicKey);
+    const second = await registerFreshNode(undefined, 0, duplicateState.publicKey);
+    const root = Buffer.alloc(32, 120);
+
+    await proposeRoot(
+      first.node,
+      anchor.web3.Keypair.generate(),
+      root,
+      undefined,
+      duplicateState.publicKey
+    );
+    try {
+      await proposeRoot(
+        second.node,
+        anchor.web3.Keypair.generate(),
+        root,
+        undefined,
+        duplicateState.publicKey
+      );
+      assert.fail("a duplicate root should be rejected while the first is open");
+    } catch (err) {
+      assert.include(err.toString(), "DuplicateProposal");
+    }
+
+    // Other roots are unaffected
+    await proposeRoot(
+      second.node,
+      anchor.web3.Keypair.generate(),
+      Buffer.alloc(32, 121),
+      undefined,
+      duplicateState.publicKey
+    );
+  });
+
+  it("Refuses new proposals while every pending root slot is open", async () => {
+    const fullState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: fullState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([fullState])
+      .rpc();
+    const proposer = await registerFreshNode(undefined, 0, fullState.publicKey);
+    const voter = await registerFreshNode(undefined, 0, fullState.publicKey);
+
+    // Without a TTL none of these close on their own
+    const open = [];
+    for (let i = 0; i < 16; i++) {
+      const proposal = anchor.web3.Keypair.generate();
+      await proposeRoot(
+        proposer.node,
+        proposal,
+        Buffer.alloc(32, 160 + i),
+        undefined,
+        fullState.publicKey
+      );
+      open.push(proposal.publicKey);
+    }
+
+    // Evicting the oldest would let its root be proposed a second time
+    try {
+      await proposeRoot(
+        voter.node,
+        anchor.web3.Keypair.generate(),
+        Buffer.alloc(32, 160),
+        undefined,
+        fullState.publicKey
+      );
+      assert.fail("a full pending root buffer should refuse new proposals");
+    } catch (err) {
+      assert.include(err.toString(), "PendingRootsFull");
+    }
+
+    // Deciding one frees its slot
+    await castVote(voter.node, open[0], fullState.publicKey);
+    await proposeRoot(
+      voter.node,
+      anchor.web3.Keypair.generate(),
+      Buffer.alloc(32, 176),
+      undefined,
+      fullState.publicKey
+    );
+  });
+
+  it("Emits the same numeric proposal id on creation and consensus", async () => {
+    const idState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: idState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([idState])
+      .rpc();
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const eventsOf = async (signature: string) => {
+      await provider.connection.confirmTransaction(signature, "confirmed");
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return [...parser.parseLogs(tx.meta.logMessages)];
+    };
+
+    const nodes = [];
+    for (let i = 0; i < 2; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, idState.publicKey));
+    }
+    // Burn id 0 so the id under test differs from the default
+    await proposeRoot(
+      nodes[0].node,
+      anchor.web3.Keypair.generate(),
+      Buffer.alloc(32, 130),
+      undefined,
+      idState.publicKey
+    );
+
+    const proposal = anchor.web3.Keypair.generate();
+    const created = (
+      await eventsOf(
+        await proposeRoot(
+          nodes[0].node,
+          proposal,
+          Buffer.alloc(32, 131),
+          undefined,
+          idState.publicKey
+        )
+      )
+    ).find((e) => e.name === "ProposalCreated");
+    const reached = (
+      await eventsOf(await castVote(nodes[1].node, proposal.publicKey, idState.publicKey))
+    ).find((e) => e.name === "ConsensusReached");
+
+    assert.equal(created.data.proposalId.toString(), "1");
+    assert.equal(reached.data.proposalId.toString(), created.data.proposalId.toString());
+    assert.equal(reached.data.proposal.toString(), proposal.publicKey.toString());
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.proposalId.toString(), "1");
+  });
+
+  it("Tracks total staked across stake changes", async () => {
+    const tvlState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: tvlState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([tvlState])
+      .rpc();
+    const vault = await vaultFor(tvlState.publicKey);
+    const nodes = [
+      await registerFreshNode(new anchor.BN(1500000), 0, tvlState.publicKey),
+      await registerFreshNode(new anchor.BN(2000000), 0, tvlState.publicKey),
+      await registerFreshNode(new anchor.BN(3000000), 0, tvlState.publicKey),
+    ];
+    const updateStake = (
+      { node, nodeState, tokenAccount }: (typeof nodes)[number],
+      amount: number,
+      increase: boolean
+    ) =>
+      program.methods
+        .updateStake(new anchor.BN(amount), increase)
+        .accounts({
+          protocolState: tvlState.publicKey,
+          nodeState,
+          staker: node.publicKey,
+          stakerTokenAccount: tokenAccount,
+          protocolVault: vault,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([node])
+        .rpc();
+
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      nodes[0].tokenAccount,
+      provider.wallet.payer,
+      500000
+    );
+    await updateStake(nodes[0], 500000, true);
+    await updateStake(nodes[1], 300000, false);
+    await program.methods
+      .slashNode(1)
+      .accounts({
+        protocolState: tvlState.publicKey,
+        nodeState: nodes[2].nodeState,
+        proposal: null,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+    await program.methods
+      .deregisterNode()
+      .accounts({
+        protocolState: tvlState.publicKey,
+        nodeState: nodes[0].nodeState,
+        staker: nodes[0].node.publicKey,
+      })
+      .signers([nodes[0].node])
+      .rpc();
+
+    let sum = new anchor.BN(0);
+    for (const { nodeState } of nodes) {
+      sum = sum.add((await program.account.nodeState.fetch(nodeState)).stake);
+    }
+    const state = await program.account.protocolState.fetch(tvlState.publicKey);
+    // 1.7 tokens left after the decrease plus 2.7 after the 10% slash
+    assert.equal(sum.toString(), "4400000");
+    assert.equal(state.totalStaked.toString(), sum.toString());
+
+    const { events } = await program.methods
+      .emitProtocolTvl()
+      .accounts({ protocolState: tvlState.publicKey })
+      .simulate();
+    const tvl = events.find((e) => e.name === "ProtocolTvl");
+    assert.equal(tvl.data.totalStaked.toString(), sum.toString());
+    assert.equal(tvl.data.activeNodeCount.toString(), "2");
+  });
+
+  it("Finalizes proposals only after an unchallenged window", async () => {
+    const disputeState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: disputeState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([disputeState])
+      .rpc();
+    await program.methods
+      .setChallengeParams(new anchor.BN(2), new anchor.BN(100000), new anchor.BN(3600))
+      .accounts({
+        protocolState: disputeState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+    const vault = await vaultFor(disputeState.publicKey);
+    const proposer = await registerFreshNode(undefined, 0, disputeState.publicKey);
+    const voter = await registerFreshNode(undefined, 0, disputeState.publicKey);
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [disputeState.publicKey.toBuffer()],
+      program.programId
+    );
+    const challengerTokens = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      provider.wallet.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      challengerTokens,
+      provider.wallet.payer,
+      100000
+    );
+
+    const accept = async (root: number) => {
+      const proposal = anchor.web3.Keypair.generate();
+      await proposeRoot(
+        proposer.node,
+        proposal,
+        Buffer.alloc(32, root),
+        undefined,
+        disputeState.publicKey
+      );
+      await castVote(voter.node, proposal.publicKey, disputeState.publicKey);
+      return proposal.publicKey;
+    };
+    const finalize = (proposal: anchor.web3.PublicKey) =>
+      program.methods
+        .finalizeProposal()
+        .accounts({
+          protocolState: disputeState.publicKey,
+          proposal,
+          proposerState: await proposerStateOf(proposal, disputeState.publicKey),
+          executor: provider.wallet.publicKey,
+        })
+        .rpc();
+    const challenge = (proposal: anchor.web3.PublicKey) =>
+      program.methods
+        .challengeProposal()
+        .accounts({
+          protocolState: disputeState.publicKey,
+          proposal,
+          challenger: provider.wallet.publicKey,
+          challengerTokenAccount: challengerTokens,
+          protocolVault: vault,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    const settleDispute = (proposal: anchor.web3.PublicKey, upheld: boolean) =>
+      program.methods
+        .resolveChallenge(upheld)
+        .accounts({
+          protocolState: disputeState.publicKey,
+          proposal,
+          admin: provider.wallet.publicKey,
+          challengerTokenAccount: challengerTokens,
+          protocolVault: vault,
+          vaultAuthority,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    const challengerBalance = async () =>
+      (await getAccount(provider.connection, challengerTokens)).amount;
+
+    // Consensus alone no longer executes; the window has to close first
+    const accepted = await accept(120);
+    let proposalState = await program.account.proposal.fetch(accepted);
+    assert.equal(proposalState.consensusReached, 1);
+    assert.equal(proposalState.executed, 0);
+    try {
+      await finalize(accepted);
+      assert.fail("finalizing inside the challenge window should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ChallengeWindowOpen");
+    }
+
+    // A dispute opened inside the window holds the proposal past it
+    const disputed = await accept(121);
+    await challenge(disputed);
+    assert.equal(await challengerBalance(), BigInt(0));
+
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await finalize(accepted);
+    proposalState = await program.account.proposal.fetch(accepted);
+    assert.equal(proposalState.executed, 1);
+
+    try {
+      await finalize(disputed);
+      assert.fail("a challenged proposal should not finalize");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalUnderChallenge");
+    }
+
+    // Upholding the challenge rejects the proposal and returns the bond
+    await settleDispute(disputed, true);
+    proposalState = await program.account.proposal.fetch(disputed);
+    assert.equal(proposalState.rejected, 1);
+    assert.equal(proposalState.executed, 0);
+    assert.equal(await challengerBalance(), BigInt(100000));
+
+    // Dismissing one forfeits the bond and lets the proposal finalize
+    const dismissed = await accept(122);
+    await challenge(dismissed);
+    await settleDispute(dismissed, false);
+    let state = await program.account.protocolState.fetch(disputeState.publicKey);
+    assert.equal(state.forfeitedBonds.toString(), "100000");
+    assert.equal(await challengerBalance(), BigInt(0));
+
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await finalize(dismissed);
+    proposalState = await program.account.proposal.fetch(dismissed);
+    assert.equal(proposalState.executed, 1);
+
+    // Forfeited bonds are recoverable by the admin, and only up to their total
+    const withdraw = (amount: number) =>
+      program.methods
+        .withdrawForfeitedBonds(new anchor.BN(amount))
+        .accounts({
+          protocolState: disputeState.publicKey,
+          admin: provider.wallet.publicKey,
+          recipientTokenAccount: challengerTokens,
+          protocolVault: vault,
+          vaultAuthority,
+          stakeMint: synapseToken,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    try {
+      await withdraw(100001);
+      assert.fail("withdrawing past the forfeited bonds should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InsufficientForfeitedBonds");
+    }
+    await withdraw(100000);
+    state = await program.account.protocolState.fetch(disputeState.publicKey);
+    assert.equal(state.forfeitedBonds.toString(), "0");
+    assert.equal(await challengerBalance(), BigInt(100000));
+  });
+
+  it("Lets anyone expire a challenge the renounced admin can't resolve", async () => {
+    const orphanState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: orphanState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([orphanState])
+      .rpc();
+    await program.methods
+      .setChallengeParams(new anchor.BN(2), new anchor.BN(100000), new anchor.BN(3))
+      .accounts({
+        protocolState: orphanState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+    const vault = await vaultFor(orphanState.publicKey);
+    const proposer = await registerFreshNode(undefined, 0, orphanState.publicKey);
+    const voter = await registerFreshNode(undefined, 0, orphanState.publicKey);
+    const challengerTokens = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      provider.wallet.publicKey,
+      anchor.web3.Keypair.generate()
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      synapseToken,
+      challengerTokens,
+      provider.wallet.payer,
+      100000
+    );
+
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      proposer.node,
+      proposal,
+      Buffer.alloc(32, 125),
+      undefined,
+      orphanState.publicKey
+    );
+    await castVote(voter.node, proposal.publicKey, orphanState.publicKey);
+    await program.methods
+      .challengeProposal()
+      .accounts({
+        protocolState: orphanState.publicKey,
+        proposal: proposal.publicKey,
+        challenger: provider.wallet.publicKey,
+        challengerTokenAccount: challengerTokens,
+        protocolVault: vault,
+        stakeMint: synapseToken,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    await program.methods
+      .renounceAdmin()
+      .accounts({
+        protocolState: orphanState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    const expire = () =>
+      program.methods
+        .expireChallenge()
+        .accounts({ protocolState: orphanState.publicKey, proposal: proposal.publicKey })
+        .rpc();
+    try {
+      await expire();
+      assert.fail("the challenge should wait out its resolution period");
+    } catch (err) {
+      assert.include(err.toString(), "ChallengeResolutionPending");
+    }
+
+    // With no admin left the dispute times out and the bond is forfeited
+    await new Promise((resolve) => setTimeout(resolve, 4000));
+    await expire();
+    await program.methods
+      .finalizeProposal()
+      .accounts({
+        protocolState: orphanState.publicKey,
+        proposal: proposal.publicKey,
+        proposerState: await proposerStateOf(proposal.publicKey, orphanState.publicKey),
+        executor: provider.wallet.publicKey,
+      })
+      .rpc();
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.executed, 1);
+    const state = await program.account.protocolState.fetch(orphanState.publicKey);
+    assert.equal(state.forfeitedBonds.toString(), "100000");
+  });
+
+  it("Stakes a Token-2022 mint net of transfer fees", async () => {
+    const feeState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: feeState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([feeState])
+      .rpc();
+
+    // 1% transfer fee, withheld in the receiving account
+    const mint = anchor.web3.Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferFeeConfigInstruction(
+          mint.publicKey,
+          provider.wallet.publicKey,
+          provider.wallet.publicKey,
+          100,
+          BigInt(1000000),
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(
+          mint.publicKey,
+          9,
+          provider.wallet.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [mint]
+    );
+
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [feeState.publicKey.toBuffer()],
+      program.programId
+    );
+    const vault = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint.publicKey,
+      vaultAuthority,
+      anchor.web3.Keypair.generate(),
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await program.methods
+      .setProtocolVault()
+      .accounts({
+        protocolState: feeState.publicKey,
+        admin: provider.wallet.publicKey,
+        protocolVault: vault,
+        vaultAuthority,
+      })
+      .rpc();
+
+    const node = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(node.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+    const tokenAccount = await createAccount(
+      provider.connection,
+      provider.wallet.payer,
+      mint.publicKey,
+      node.publicKey,
+      anchor.web3.Keypair.generate(),
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      mint.publicKey,
+      tokenAccount,
+      provider.wallet.payer,
+      2000000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const nodeState = await findNodeState(node.publicKey, feeState.publicKey);
+    await program.methods
+      .registerNode(
+        new anchor.BN(2000000),
+        Buffer.alloc(32, 1),
+        0,
+        CLIENT_VERSION,
+        "",
+        Buffer.alloc(32)
+      )
+      .accounts({
+        protocolState: feeState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+        stakerTokenAccount: tokenAccount,
+        protocolVault: vault,
+        stakeMint: mint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([node])
+      .rpc();
+
+    // Only the 1.98 tokens that reached the vault are credited
+    const state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.stake.toString(), "1980000");
+    const vaultAccount = await getAccount(
+      provider.connection,
+      vault,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(vaultAccount.amount, BigInt(1980000));
+    const protocol = await program.account.protocolState.fetch(feeState.publicKey);
+    assert.equal(protocol.totalStaked.toString(), "1980000");
+  });
+
+  it("Fails withdrawals clearly when the vault is short", async () => {
+    const shortState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: shortState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([shortState])
+      .rpc();
+    await setUnstakeCooldown(0, shortState.publicKey);
+
+    // A permanent delegate can pull tokens out of any account of its mint,
+    // the vault included, leaving it short of what the program owes
+    const mint = anchor.web3.Keypair.generate();
+    const mintLen = getMintLen([ExtensionType.PermanentDelegate]);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space: mintLen,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintLen),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializePermanentDelegateInstruction(
+          mint.publicKey,
+          provider.wallet.publicKey,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(
+          mint.publicKey,
+          9,
+          provider.wallet.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [mint]
+    );
+    const createTokenAccount = (owner: anchor.web3.PublicKey) =>
+      createAccount(
+        provider.connection,
+        provider.wallet.payer,
+        mint.publicKey,
+        owner,
+        anchor.web3.Keypair.generate(),
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [shortState.publicKey.toBuffer()],
+      program.programId
+    );
+    const vault = await createTokenAccount(vaultAuthority);
+    await program.methods
+      .setProtocolVault()
+      .accounts({
+        protocolState: shortState.publicKey,
+        admin: provider.wallet.publicKey,
+        protocolVault: vault,
+        vaultAuthority,
+      })
+      .rpc();
+
+    const node = anchor.web3.Keypair.generate();
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(node.publicKey, anchor.web3.LAMPORTS_PER_SOL)
+    );
+    const tokenAccount = await createTokenAccount(node.publicKey);
+    await mintTo(
+      provider.connection,
+      provider.wallet.payer,
+      mint.publicKey,
+      tokenAccount,
+      provider.wallet.payer,
+      1500000,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const nodeState = await findNodeState(node.publicKey, shortState.publicKey);
+    await program.methods
+      .registerNode(
+        new anchor.BN(1500000),
+        Buffer.alloc(32, 1),
+        0,
+        CLIENT_VERSION,
+        "",
+        Buffer.alloc(32)
+      )
+      .accounts({
+        protocolState: shortState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+        stakerTokenAccount: tokenAccount,
+        protocolVault: vault,
+        stakeMint: mint.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([node])
+      .rpc();
+    await program.methods
+      .deregisterNode()
+      .accounts({
+        protocolState: shortState.publicKey,
+        nodeState,
+        staker: node.publicKey,
+      })
+      .signers([node])
+      .rpc();
+
+    await transferChecked(
+      provider.connection,
+      provider.wallet.payer,
+      vault,
+      mint.publicKey,
+      await createTokenAccount(provider.wallet.publicKey),
+      provider.wallet.payer,
+      1,
+      9,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    try {
+      await program.methods
+        .claimUnstake()
+        .accounts({
+          protocolState: shortState.publicKey,
+          nodeState,
+          staker: node.publicKey,
+          stakerTokenAccount: tokenAccount,
+          protocolVault: vault,
+          vaultAuthority,
+          stakeMint: mint.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([node])
+        .rpc();
+      assert.fail("withdrawal from a short vault should be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "VaultInsufficientBalance");
+    }
+    const state = await program.account.nodeState.fetch(nodeState);
+    assert.equal(state.pendingUnstake.toString(), "1500000");
+  });
+
+  it("Keeps nodes of another protocol instance out of its proposals", async () => {
+    const initState = async () => {
+      const state = anchor.web3.Keypair.generate();
+      await program.methods
+        .initialize(MIN_STAKE, EPOCH_DURATION)
+        .accounts({
+          protocolState: state.publicKey,
+          admin: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([state])
+        .rpc();
+      return state.publicKey;
+    };
+    const realState = await initState();
+    const attackerState = await initState();
+
+    const proposer = await registerFreshNode(undefined, 0, realState);
+    await registerFreshNode(undefined, 0, realState);
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(proposer.node, proposal, Buffer.alloc(32, 140), undefined, realState);
+
+    // A node registered under an attacker-controlled instance
+    const intruder = await registerFreshNode(undefined, 0, attackerState);
+    const vote = (state: anchor.web3.PublicKey) =>
+      program.methods
+        .voteOnProposal(true)
+        .accounts({
+          protocolState: state,
+          nodeState: intruder.nodeState,
+          proposal: proposal.publicKey,
+          proposerState: proposer.nodeState,
+          voter: intruder.node.publicKey,
+        })
+        .signers([intruder.node])
+        .rpc();
+
+    for (const state of [realState, attackerState]) {
+      try {
+        await vote(state);
+        assert.fail("a foreign node should not vote on this proposal");
+      } catch (err) {
+        assert.match(err.toString(), /ConstraintSeeds|InvalidParameter/);
+      }
+    }
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.voteCount.toString(), "1");
+  });
+
+  it("Reports votes dropped when a raised min_stake deactivates the voter", async () => {
+    const droppedState = anchor.web3.Keypair.generate();
+    await program.methods
+      .initialize(MIN_STAKE, EPOCH_DURATION)
+      .accounts({
+        protocolState: droppedState.publicKey,
+        admin: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([droppedState])
+      .rpc();
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const eventsOf = async (signature: string) => {
+      await provider.connection.confirmTransaction(signature, "confirmed");
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return [...parser.parseLogs(tx.meta.logMessages)];
+    };
+
+    const nodes = [];
+    for (let i = 0; i < 3; i++) {
+      nodes.push(await registerFreshNode(undefined, 0, droppedState.publicKey));
+    }
+    const proposal = anchor.web3.Keypair.generate();
+    await proposeRoot(
+      nodes[0].node,
+      proposal,
+      Buffer.alloc(32, 150),
+      undefined,
+      droppedState.publicKey
+    );
+
+    await program.methods
+      .updateProtocolParams(new anchor.BN(2000000), EPOCH_DURATION)
+      .accounts({
+        protocolState: droppedState.publicKey,
+        admin: provider.wallet.publicKey,
+      })
+      .rpc();
+
+    // The transaction succeeds so the deactivation sticks, but the ballot is
+    // neither counted nor silent
+    const events = await eventsOf(
+      await castVote(nodes[1].node, proposal.publicKey, droppedState.publicKey)
+    );
+    const dropped = events.find((e) => e.name === "VoteDropped");
+    assert.isDefined(dropped);
+    assert.equal(dropped.data.proposal.toString(), proposal.publicKey.toString());
+    assert.equal(dropped.data.node.toString(), nodes[1].node.publicKey.toString());
+    assert.isDefined(events.find((e) => e.name === "NodeDeactivated"));
+
+    const proposalState = await program.account.proposal.fetch(proposal.publicKey);
+    assert.equal(proposalState.voteCount.toString(), "1");
+    assert.equal(proposalState.consensusReached, 0);
+    const voter = await program.account.nodeState.fetch(nodes[1].nodeState);
+    assert.equal(voter.isActive, false);
+  });
+  it("Refuses the stake vault as the reward pool", async () => {
+    const [vaultAuthority] = await anchor.web3.PublicKey.findProgramAddress(
+      [protocolState.publicKey.toBuffer()],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .setRewardParams(new anchor.BN(1))
+        .accounts({
+          protocolState: protocolState.publicKey,
+          admin: provider.wallet.publicKey,
+          rewardPool: protocolVault,
+          vaultAuthority,
+        })
+        .rpc();
+      assert.fail("the stake vault should not double as the reward pool");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidVault");
+    }
+    const state = await program.account.protocolState.fetch(protocolState.publicKey);
+    assert.notEqual(state.rewardPool.toString(), protocolVault.toString());
   });
 }); 
\ No newline at end of file